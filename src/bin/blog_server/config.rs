@@ -1,5 +1,6 @@
-use std::{time::Duration, net::SocketAddr, path::PathBuf, str};
+use std::{collections::{BTreeMap, HashMap}, time::Duration, net::SocketAddr, path::PathBuf, str};
 
+use blog::tag::{TagError, TagRegistry};
 use libshire::uuid::Uuid;
 use serde::{Deserialize, Deserializer};
 
@@ -15,13 +16,76 @@ pub(crate) struct Config {
     pub site: SiteConfig,
     pub rss: RssConfig,
     pub atom: AtomConfig,
+    #[serde(default)]
+    pub toc: TocConfig,
+    #[serde(default)]
+    pub reading: ReadingConfig,
+    #[serde(default)]
+    pub homepage: HomepageConfig,
     pub contact: Vec<ContactConfig>,
+    #[serde(rename = "author", default)]
+    pub authors: Vec<AuthorConfig>,
+    /// The known tags, keyed by their canonical names.
+    #[serde(default)]
+    pub tags: HashMap<String, TagConfig>,
+}
+
+impl Config {
+    /// Returns the author in the author registry with the given key.
+    pub(crate) fn author(&self, key: &str) -> Option<&AuthorConfig> {
+        self.authors
+            .iter()
+            .find(|author| author.key == key)
+    }
+
+    /// Returns the author of posts whose headers do not name any authors, if there is one.
+    pub(crate) fn default_author(&self) -> Option<&str> {
+        self.site.default_author
+            .as_deref()
+            .or_else(|| self.authors.first().map(|author| author.key.as_str()))
+    }
+
+    /// Returns the configuration of the tag with the given canonical name.
+    pub(crate) fn tag(&self, name: &str) -> Option<&TagConfig> {
+        self.tags.get(name)
+    }
+
+    /// Returns the label to show for a tag, which is its canonical name unless the tag's config
+    /// gives a different one.
+    pub(crate) fn tag_label<'a>(&'a self, name: &'a str) -> &'a str {
+        self.tag(name)
+            .and_then(|tag| tag.label.as_deref())
+            .unwrap_or(name)
+    }
+
+    /// Builds the registry of known tags and their aliases from the tags config. The tags are
+    /// inserted in order of name, so that the same tag is reported if two tags' aliases conflict.
+    pub(crate) fn tag_registry(&self) -> Result<TagRegistry, (String, TagError)> {
+        let mut registry = TagRegistry::new();
+        for (name, tag) in self.tags.iter().collect::<BTreeMap<_, _>>() {
+            registry.insert(name, tag.aliases.iter().map(String::as_str))
+                .map_err(|err| (name.clone(), err))?;
+        }
+        Ok(registry)
+    }
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub(crate) struct SiteConfig {
     pub protocol: String,
     pub domain: String,
+    #[serde(default = "default_lang")]
+    pub lang: String,
+    /// The author of posts whose headers do not name any authors. If this is not given, the first
+    /// author in the author registry is used.
+    pub default_author: Option<String>,
+}
+
+impl SiteConfig {
+    /// Returns the absolute URL for the given path on this site. The path should start with `/`.
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}://{}{}", self.protocol, self.domain, path)
+    }
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -51,6 +115,59 @@ pub(crate) struct AtomConfig {
     pub title: String,
 }
 
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct TocConfig {
+    /// The number of words of prose a post must have for its table of contents to be shown,
+    /// unless the post's header says otherwise.
+    pub min_words: usize,
+}
+
+impl Default for TocConfig {
+    fn default() -> Self {
+        Self { min_words: 1500 }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct ReadingConfig {
+    /// The reading speed used to estimate how long it takes to read a post.
+    pub words_per_minute: u32,
+}
+
+impl Default for ReadingConfig {
+    fn default() -> Self {
+        Self { words_per_minute: 200 }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct HomepageConfig {
+    /// The maximum number of pinned posts to show on the homepage.
+    pub pinned_posts: usize,
+    /// The number of recent posts to show on the homepage, after any pinned posts.
+    pub recent_posts: usize,
+}
+
+impl Default for HomepageConfig {
+    fn default() -> Self {
+        Self {
+            pinned_posts: 3,
+            recent_posts: 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct TagConfig {
+    pub label: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub(crate) struct ContactConfig {
     pub name: String,
@@ -58,6 +175,17 @@ pub(crate) struct ContactConfig {
     pub url: Option<String>,
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct AuthorConfig {
+    pub key: String,
+    pub name: String,
+    pub bio: Option<String>,
+    pub avatar: Option<String>,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub rel_me: Vec<String>,
+}
+
 impl str::FromStr for Config {
     type Err = toml::de::Error;
 
@@ -73,3 +201,63 @@ where
     u64::deserialize(deserializer)
         .map(Duration::from_millis)
 }
+
+fn default_lang() -> String {
+    "en".to_owned()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::Path;
+
+    use super::Config;
+
+    /// Returns a config whose content directories are all `dir`. The site config comes last, so
+    /// `extra` can add keys to it as well as adding tables.
+    pub(crate) fn config(dir: &Path, extra: &str) -> Config {
+        format!(
+            "bind = \"127.0.0.1:8080\"\n\
+             concurrency_limit = 1\n\
+             fs_event_delay_millis = 0\n\
+             namespace_uuid = \"00000000-0000-0000-0000-000000000000\"\n\
+             contact = []\n\
+             [content]\n\
+             static_dir = {dir:?}\nfavicon_dir = {dir:?}\nrobots_path = {dir:?}\n\
+             posts_dir = {dir:?}\npost_media_dir = {dir:?}\n\
+             [github]\n\
+             [rss]\nnum_posts = 1\ntitle = \"Blog\"\nttl = 1\n\
+             [atom]\nnum_posts = 1\ntitle = \"Blog\"\n\
+             [site]\nprotocol = \"http\"\ndomain = \"localhost\"\n\
+             {extra}",
+            dir = dir.to_string_lossy(),
+            extra = extra
+        ).parse().unwrap()
+    }
+
+    const AUTHORS: &str = "\
+        [[author]]\nkey = \"ann\"\nname = \"Ann\"\n\
+        [[author]]\nkey = \"bob\"\nname = \"Bob\"\nrel_me = [\"https://example.com/@bob\"]\n";
+
+    #[test]
+    fn authors_are_found_by_key() {
+        let config = config(Path::new("."), AUTHORS);
+
+        assert_eq!(config.author("ann").map(|author| author.name.as_str()), Some("Ann"));
+        assert_eq!(config.author("bob").map(|author| author.rel_me.as_slice()), Some(&["https://example.com/@bob".to_owned()][..]));
+        assert!(config.author("Ann").is_none());
+        assert!(config.author("carol").is_none());
+        assert!(config.author("").is_none());
+    }
+
+    #[test]
+    fn default_author() {
+        assert_eq!(config(Path::new("."), "").default_author(), None);
+        assert_eq!(config(Path::new("."), AUTHORS).default_author(), Some("ann"));
+
+        let with_default = format!("default_author = \"bob\"\n{}", AUTHORS);
+        assert_eq!(config(Path::new("."), &with_default).default_author(), Some("bob"));
+
+        // The default author does not have to be in the author registry.
+        assert_eq!(config(Path::new("."), "default_author = \"carol\"\n").default_author(), Some("carol"));
+    }
+}
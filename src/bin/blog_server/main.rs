@@ -4,6 +4,11 @@ mod fs_watcher;
 mod render;
 mod service;
 mod template;
+mod url;
+
+#[cfg(test)]
+#[path = "../../lib/test_util.rs"]
+mod test_util;
 
 use std::{
     env,
@@ -25,6 +30,8 @@ use tracing::{error, info};
 use blog::{
     codeblock::CodeBlockRenderer,
     db::ConcurrentPostsStore,
+    shortcode::ShortcodeRegistry,
+    tag::TagError,
 };
 
 use config::Config;
@@ -57,15 +64,23 @@ fn run() -> Result<(), Error> {
             .map_err(Error::BadConfig)?
     };
 
+    // Build the registry of known tags, which both the posts store and the renderer use to
+    // resolve the tags of posts.
+    let tags = config.tag_registry()
+        .map_err(|(tag, err)| Error::BadTag(tag, err))?;
+
     // Create the global context that will be used and modified throughout the program.
-    let context = Arc::new(Context::new(config, ConcurrentPostsStore::new()));
+    let context = Arc::new(Context::new(config, ConcurrentPostsStore::with_tags(tags.clone())));
 
     let code_renderer = CodeBlockRenderer::new();
+    let shortcodes = ShortcodeRegistry::new();
 
     // Create the post renderer and the mpsc channel that will be used to communicate with it.
     let (renderer, tx) = Renderer::new(
         context.clone(),
         code_renderer,
+        shortcodes,
+        tags,
         context.config().content.posts_dir.clone()
     );
 
@@ -160,6 +175,7 @@ enum Error {
     NoConfig,
     ReadConfig(io::Error),
     BadConfig(toml::de::Error),
+    BadTag(String, TagError),
     CreateWatcher(notify::Error),
     WatchDir(PathBuf, notify::Error),
     TokioRuntime(io::Error),
@@ -179,6 +195,9 @@ impl fmt::Display for Error {
             Self::BadConfig(err) => {
                 write!(f, "error in config: {}", err)
             },
+            Self::BadTag(tag, err) => {
+                write!(f, "error in config for tag `{}`: {}", tag, err)
+            },
             Self::CreateWatcher(err) => {
                 write!(f, "failed to create filesystem watcher: {}", err)
             },
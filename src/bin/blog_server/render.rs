@@ -1,18 +1,22 @@
 use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
     fmt,
     fs,
     io::{self, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, mpsc},
+    time::Duration,
 };
 
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use notify::DebouncedEvent;
 use tracing::{info, warn, error};
 
 use blog::{
     codeblock::CodeBlockRenderer,
-    post::{Error as ParseError, Post, Id},
+    post::{AssetRoot, Diagnostic, Error as ParseError, Header, LinkTargets, Post, PostSource, Id, Severity},
+    shortcode::ShortcodeRegistry,
+    tag::TagRegistry,
 };
 
 use crate::Context;
@@ -20,6 +24,8 @@ use crate::Context;
 pub(crate) struct Renderer {
     context: Arc<Context>,
     code_renderer: CodeBlockRenderer,
+    shortcodes: ShortcodeRegistry,
+    tags: TagRegistry,
     posts_dir_path: PathBuf,
     rx: mpsc::Receiver<DebouncedEvent>,
 }
@@ -28,6 +34,8 @@ impl Renderer {
     pub(crate) fn new(
         context: Arc<Context>,
         code_renderer: CodeBlockRenderer,
+        shortcodes: ShortcodeRegistry,
+        tags: TagRegistry,
         posts_dir_path: PathBuf,
     ) -> (Self, mpsc::Sender<DebouncedEvent>)
     {
@@ -41,6 +49,8 @@ impl Renderer {
         (Self {
             context,
             code_renderer,
+            shortcodes,
+            tags,
             posts_dir_path,
             rx,
         }, tx)
@@ -48,7 +58,24 @@ impl Renderer {
 
     #[tracing::instrument(skip(self))]
     pub(crate) fn handle_events(self) {
-        while let Ok(notify_event) = self.rx.recv() {
+        loop {
+            // If there are any posts scheduled to be published in the future, only wait for
+            // filesystem events until the next one is due, so that we can wake up and publish it.
+            let notify_event = match self.time_until_next_scheduled() {
+                Some(timeout) => match self.rx.recv_timeout(timeout) {
+                    Ok(notify_event) => notify_event,
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        self.publish_scheduled(Utc::now());
+                        continue;
+                    },
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                },
+                None => match self.rx.recv() {
+                    Ok(notify_event) => notify_event,
+                    Err(_) => break,
+                },
+            };
+
             let fs_event = match notify_event {
                 // Convert create & write events for valid post file names to update events.
                 DebouncedEvent::Create(path) | DebouncedEvent::Write(path) => {
@@ -90,20 +117,82 @@ impl Renderer {
 
     fn handle_event(&self, event: &Event) {
         info!(event = ?event);
+        let now = Utc::now();
         match event {
-            Event::Update(target) => self.update(target),
-            Event::Rename(old_target, new_target) => self.rename(old_target, new_target),
-            Event::Remove(target) => self.remove(target),
-            Event::Scan => self.scan(),
+            Event::Update(target) => self.update(target, now),
+            Event::Rename(old_target, new_target) => self.rename(old_target, new_target, now),
+            Event::Remove(target) => self.remove(target, now),
+            Event::Scan => self.scan(now),
         }
     }
 
+    fn time_until_next_scheduled(&self) -> Option<Duration> {
+        let next_scheduled = self.context.posts().read_blocking().next_scheduled()?;
+
+        // `to_std` fails if the duration is negative, which means that the post is already due.
+        Some((next_scheduled - Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO))
+    }
+
+    /// Publishes the scheduled posts which are due at the given time.
     #[tracing::instrument(skip(self))]
-    fn update(&self, target: &EventTarget) {
-        match self.parse_post_from_target(target) {
+    fn publish_scheduled(&self, now: DateTime<Utc>) {
+        let (published, aliases) = {
+            let mut guard = self.context.posts().write_blocking();
+            let published = guard.publish_scheduled(now);
+            let aliases = published
+                .iter()
+                .filter_map(|id| guard.get(id))
+                .flat_map(|post| post.aliases().to_vec())
+                .collect::<Vec<_>>();
+            (published, aliases)
+        };
+
+        info!(num_published = published.len(), "Published scheduled posts");
+
+        // The newly published posts were rendered against the posts published by their publish
+        // dates, so they stay the same. Published posts could not link to them until now, so the
+        // posts linking to them need to be rendered again to resolve their links.
+        if !published.is_empty() {
+            let mut ids = published.iter().map(|id| id.as_ref()).collect::<Vec<_>>();
+            ids.extend(aliases.iter().map(|alias| &**alias));
+            self.rerender_linking(&ids, now);
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn update(&self, target: &EventTarget, now: DateTime<Utc>) {
+        if let Err(err) = self.check_duplicate_id(target) {
+            err.log();
+            return;
+        }
+
+        match self.parse_post_from_target(target, now) {
             Ok(post) => {
-                let mut guard = self.context.posts().write_blocking();
-                guard.insert(post);
+                let title = post.title().to_owned();
+                let aliases = post.aliases().to_vec();
+                let published = post.header().published();
+                let draft = post.draft();
+
+                let old_post = self.context.posts().write_blocking().insert(post, now);
+                self.check_alias_conflicts(target);
+
+                // Posts which link to the post only need to be rendered again if the post is new,
+                // or its title, aliases or when it is published have changed.
+                let link_target_changed = match old_post {
+                    Some(old_post) => old_post.title() != title
+                        || old_post.aliases() != aliases
+                        || old_post.header().published() != published
+                        || old_post.draft() != draft,
+                    None => true,
+                };
+
+                if link_target_changed {
+                    let mut ids = vec![target.id.as_ref()];
+                    ids.extend(aliases.iter().map(|alias| &**alias));
+                    self.rerender_linking(&ids, now);
+                }
             },
             Err(err) => {
                 err.log();
@@ -112,13 +201,98 @@ impl Renderer {
     }
 
     #[tracing::instrument(skip(self))]
-    fn rename(&self, old_target: &EventTarget, new_target: &EventTarget) {
-        let post_res = self.parse_post_from_target(new_target);
-        let mut guard = self.context.posts().write_blocking();
-        guard.remove(&old_target.id);
-        match post_res {
+    fn rename(&self, old_target: &EventTarget, new_target: &EventTarget, now: DateTime<Utc>) {
+        let post_res = self.check_duplicate_id(new_target)
+            .and_then(|_| self.parse_post_from_target(new_target, now));
+
+        let aliases = match &post_res {
+            Ok(post) => post.aliases().to_vec(),
+            Err(_) => Vec::new(),
+        };
+
+        // If the old file was ignored because another file has the same ID, the post with that
+        // ID was read from the other file and must be kept.
+        let remove_old = self.check_duplicate_id(old_target).is_ok();
+
+        {
+            let mut guard = self.context.posts().write_blocking();
+            if remove_old {
+                guard.remove(&old_target.id);
+            }
+            match post_res {
+                Ok(post) => {
+                    guard.insert(post, now);
+                },
+                Err(err) => {
+                    err.log();
+                },
+            }
+        }
+
+        self.check_alias_conflicts(new_target);
+
+        let mut ids = vec![old_target.id.as_ref(), new_target.id.as_ref()];
+        ids.extend(aliases.iter().map(|alias| &**alias));
+        self.rerender_linking(&ids, now);
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn remove(&self, target: &EventTarget, now: DateTime<Utc>) {
+        // If the file was ignored because another file has the same ID, the post with that ID was
+        // read from the other file and must be kept.
+        if self.check_duplicate_id(target).is_err() {
+            return;
+        }
+
+        let old_post = self.context.posts().write_blocking().remove(&target.id);
+        if old_post.is_some() {
+            self.rerender_linking(&[target.id.as_ref()], now);
+        }
+    }
+
+    /// Returns an error if a post with the same ID as the target was read from a different file
+    /// which still exists, so that the target does not silently replace it.
+    fn check_duplicate_id(&self, target: &EventTarget) -> Result<(), Error> {
+        let guard = self.context.posts().read_blocking();
+
+        let other_file_name = guard
+            .get_any(target.id.as_ref())
+            .and_then(|post| post.file_name())
+            .filter(|file_name| *file_name != target.file_name);
+
+        match other_file_name {
+            Some(other_file_name) if self.posts_dir_path.join(other_file_name).is_file() => {
+                Err(Error::DuplicateId(target.path.clone(), other_file_name.to_owned()))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Renders the posts which link to any of the given IDs again, so that their wiki links point
+    /// to the right posts and have the right text, and their broken links are reported. The posts
+    /// which are rendered again do not cause the posts which link to them to be rendered again,
+    /// since their titles do not change.
+    fn rerender_linking(&self, ids: &[&str], now: DateTime<Utc>) {
+        let linking = self.context.posts()
+            .read_blocking()
+            .iter_all()
+            .filter(|post| !ids.contains(&post.id().as_ref()))
+            .filter(|post| post.linked_posts().iter().any(|linked| ids.contains(&linked.as_ref())))
+            .filter_map(|post| self.post_target(post))
+            .collect::<Vec<_>>();
+
+        for target in linking {
+            info!(post = ?target, "Rendering linking post again");
+            self.rerender(&target, now);
+        }
+    }
+
+    /// Reads and renders the post file again, replacing the post in the store.
+    fn rerender(&self, target: &EventTarget, now: DateTime<Utc>) {
+        match self.parse_post_from_target(target, now) {
             Ok(post) => {
-                guard.insert(post);
+                self.context.posts().write_blocking().insert(post, now);
+                self.check_alias_conflicts(target);
             },
             Err(err) => {
                 err.log();
@@ -126,14 +300,14 @@ impl Renderer {
         }
     }
 
-    #[tracing::instrument(skip(self))]
-    fn remove(&self, target: &EventTarget) {
-        let mut guard = self.context.posts().write_blocking();
-        guard.remove(&target.id);
+    /// Returns the target for the file which the post was read from.
+    fn post_target(&self, post: &Post) -> Option<EventTarget> {
+        post.file_name()
+            .and_then(|file_name| EventTarget::from_path(self.posts_dir_path.join(file_name)))
     }
 
     #[tracing::instrument(skip(self))]
-    fn scan(&self) {
+    fn scan(&self, now: DateTime<Utc>) {
         let posts_dir = match fs::read_dir(&self.posts_dir_path) {
             Ok(posts_dir) => posts_dir,
             Err(err) => {
@@ -142,7 +316,7 @@ impl Renderer {
             },
         };
 
-        let mut posts = Vec::new();
+        let mut sources = Vec::new();
 
         for dir_entry in posts_dir {
             let dir_entry = match dir_entry {
@@ -154,24 +328,170 @@ impl Renderer {
             };
 
             if let Some(target) = EventTarget::from_path(dir_entry.path()) {
-                posts.push(match self.parse_post_from_target(&target) {
-                    Ok(post) => post,
-                    Err(err) => {
-                        err.log();
-                        continue;
-                    },
-                });
+                match self.read_source(&target) {
+                    Ok((source, modified)) => sources.push((target, source, modified)),
+                    Err(err) => err.log(),
+                }
             }
         }
-        
-        let mut guard = self.context.posts().write_blocking();
-        guard.clear();
-        for post in posts {
-            guard.insert(post);
+
+        // Several files can have the same ID, such as `foo.md` and `foo.toml.md`. Only the first of
+        // them by name is used, so that which one is shown does not depend on the order the
+        // directory is read in.
+        sources.sort_by(|(a, ..), (b, ..)| a.file_name.cmp(&b.file_name));
+        let mut file_names = HashMap::<Id, String>::new();
+        sources.retain(|(target, ..)| match file_names.get(&target.id) {
+            Some(other_file_name) => {
+                Error::DuplicateId(target.path.clone(), other_file_name.clone()).log();
+                false
+            },
+            None => {
+                file_names.insert(target.id.clone(), target.file_name.clone());
+                true
+            },
+        });
+
+        // Every post has to be read before any of them are rendered, so that wiki links can be
+        // resolved regardless of the order the posts are read in. Posts can only link to posts
+        // which are published by the time they are, so the link targets are built once for each
+        // of the times the posts need.
+        let link_targets = sources
+            .iter()
+            .map(|(_, source, _)| source.header().links_published_by(now))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|published_by| {
+                let mut link_targets = self.new_link_targets();
+                for (target, source, _) in &sources {
+                    if can_link_to(source.header(), published_by) {
+                        insert_link_target(&mut link_targets, &target.id, source);
+                    }
+                }
+                (published_by, link_targets)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut posts = Vec::new();
+
+        for (target, source, modified) in sources {
+            let link_targets = &link_targets[&source.header().links_published_by(now)];
+
+            match self.render_source(&target, source, modified, link_targets) {
+                Ok(post) => posts.push((target, post)),
+                Err(err) => err.log(),
+            }
+        }
+
+        let targets = {
+            let mut guard = self.context.posts().write_blocking();
+            guard.clear();
+            posts
+                .into_iter()
+                .map(|(target, post)| {
+                    guard.insert(post, now);
+                    target
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for target in &targets {
+            self.check_alias_conflicts(target);
         }
     }
 
-    fn parse_post_from_target(&self, target: &EventTarget) -> Result<Post, Error> {
+    /// Logs an error for each of the post's aliases which another published post also has, since
+    /// the alias can only redirect to one of them.
+    fn check_alias_conflicts(&self, target: &EventTarget) {
+        let guard = self.context.posts().read_blocking();
+        for (alias, other_id) in guard.alias_conflicts(&target.id) {
+            error!(
+                path = %target.path.to_string_lossy(),
+                alias = %alias,
+                other_post = %other_id,
+                "Another post has the same alias, so it redirects to whichever was published first"
+            );
+        }
+    }
+
+    /// Logs a warning for each of the post's tags which is invalid, or which is not in the tags
+    /// config. Unknown tags are only reported if the config lists any tags.
+    fn check_tags(&self, target: &EventTarget, source: &PostSource) {
+        let path = target.path.to_string_lossy();
+
+        for tag in source.header().tags() {
+            match self.tags.resolve(tag) {
+                Ok(resolved) => {
+                    if !self.tags.is_empty() && !self.tags.is_known(&resolved) {
+                        warn!(path = %path, tag = %tag, "Unknown tag, not listed in the tags config");
+                    }
+                },
+                Err(err) => {
+                    warn!(path = %path, tag = %tag, error = %err, "Invalid tag, it will be ignored");
+                },
+            }
+        }
+    }
+
+    /// Logs a warning for each of the post's extra stylesheets and scripts which does not exist,
+    /// since the page would otherwise silently fail to load it.
+    fn check_assets(&self, target: &EventTarget, source: &PostSource) {
+        let path = target.path.to_string_lossy();
+        let content_config = &self.context.config().content;
+
+        let assets = source.header().styles()
+            .iter()
+            .chain(source.header().scripts());
+
+        for asset in assets {
+            let root = match asset.root() {
+                AssetRoot::Media => &content_config.post_media_dir,
+                AssetRoot::Static => &content_config.static_dir,
+            };
+
+            if !root.join(asset.path().trim_start_matches('/')).is_file() {
+                warn!(
+                    path = %path,
+                    asset = %asset.path(),
+                    root = %root.to_string_lossy(),
+                    "Post asset not found"
+                );
+            }
+        }
+    }
+
+    /// Creates a set of link targets containing no posts, which checks links to post media and
+    /// static files against the directories they are served from.
+    fn new_link_targets(&self) -> LinkTargets {
+        let content_config = &self.context.config().content;
+        LinkTargets::new()
+            .with_media_dir(content_config.post_media_dir.clone())
+            .with_static_dir(content_config.static_dir.clone())
+    }
+
+    fn parse_post_from_target(&self, target: &EventTarget, now: DateTime<Utc>) -> Result<Post, Error> {
+        let (source, modified) = self.read_source(target)?;
+
+        // Posts can only link to posts which are published by the time they are, so that they do
+        // not reveal the titles of unpublished posts or send readers to pages which do not exist
+        // yet. Drafts can link to any post.
+        let published_by = source.header().links_published_by(now);
+
+        let mut link_targets = self.new_link_targets();
+        for post in self.context.posts().read_blocking().iter_all() {
+            if !can_link_to(post.header(), published_by) {
+                continue;
+            }
+            let aliases = post.aliases().iter().map(|alias| &**alias);
+            link_targets.insert(post.id().clone(), post.title(), aliases);
+        }
+
+        // The post being parsed may not be in the store yet, or may have a different title.
+        insert_link_target(&mut link_targets, &target.id, &source);
+
+        self.render_source(target, source, modified, &link_targets)
+    }
+
+    fn read_source(&self, target: &EventTarget) -> Result<(PostSource, Option<DateTime<Utc>>), Error> {
         let mut fd = fs::OpenOptions::new()
             .read(true)
             .open(&target.path)
@@ -184,7 +504,7 @@ impl Renderer {
             return Err(Error::NotAFile);
         }
     
-        let updated = metadata
+        let modified = metadata
             .modified()
             .ok()
             .map(DateTime::from);
@@ -197,17 +517,71 @@ impl Renderer {
         };
     
         drop(fd);
+
+        if PostSource::post_id(&target.file_name, &contents).is_none() {
+            return Err(Error::NotAPost);
+        }
+
+        let source = contents
+            .parse::<PostSource>()
+            .map_err(|err| Error::Parsing(target.path.clone(), Box::new(err)))?;
+
+        Ok((source, modified))
+    }
+
+    fn render_source(
+        &self,
+        target: &EventTarget,
+        source: PostSource,
+        modified: Option<DateTime<Utc>>,
+        link_targets: &LinkTargets
+    ) -> Result<Post, Error>
+    {
+        for warning in source.warnings() {
+            log_diagnostic(&target.path, warning);
+        }
+
+        self.check_tags(target, &source);
+        self.check_assets(target, &source);
     
-        Post::new_from_str(
+        let mut post = Post::new_from_source(
             &self.code_renderer,
+            &self.shortcodes,
+            link_targets,
             self.context.config().namespace_uuid,
             target.id.clone(),
-            updated,
-            &contents
-        ).map_err(|err| Error::Parsing(Box::new(err)))
+            modified,
+            source
+        ).map_err(|err| Error::Parsing(target.path.clone(), Box::new(err)))?
+        .with_file_name(target.file_name.clone());
+
+        if let Some(author) = self.context.config().default_author() {
+            post = post.with_default_author(author);
+        }
+
+        for diagnostic in post.diagnostics() {
+            log_diagnostic(&target.path, diagnostic);
+        }
+
+        Ok(post)
     }
 }
 
+/// Returns whether a post with the given header can be linked to by posts which can only link to
+/// posts published by the given time, if any.
+fn can_link_to(header: &Header, published_by: Option<DateTime<Utc>>) -> bool {
+    match published_by {
+        Some(published_by) => header.is_published_at(published_by),
+        None => true,
+    }
+}
+
+fn insert_link_target(link_targets: &mut LinkTargets, id: &Id, source: &PostSource) {
+    let header = source.header();
+    let aliases = header.aliases().iter().map(|alias| &**alias);
+    link_targets.insert(id.clone(), header.title(), aliases);
+}
+
 #[derive(Debug)]
 enum Event {
     Update(EventTarget),
@@ -218,6 +592,7 @@ enum Event {
 
 struct EventTarget {
     path: PathBuf,
+    file_name: String,
     id: Id,
 }
 
@@ -229,20 +604,23 @@ impl fmt::Debug for EventTarget {
 
 impl EventTarget {
     fn from_path(path: PathBuf) -> Option<Self> {
-        path.file_name()
-            .and_then(|file_name| file_name.to_str())
-            .and_then(Id::from_file_name)
-            .map(|id| Self {
-                path,
-                id,
-            })
+        let file_name = path.file_name()?.to_str()?.to_owned();
+        let id = Id::from_file_name(&file_name)?;
+
+        Some(Self {
+            path,
+            file_name,
+            id,
+        })
     }
 }
 
 pub(crate) enum Error {
     Io(Box<io::Error>),
     NotAFile,
-    Parsing(Box<ParseError>),
+    NotAPost,
+    DuplicateId(PathBuf, String),
+    Parsing(PathBuf, Box<ParseError>),
 }
 
 impl Error {
@@ -254,9 +632,114 @@ impl Error {
             Error::NotAFile => {
                 warn!("Event target is not a regular file");
             },
-            Error::Parsing(err) => {
-                warn!(error = %err, "Parsing error while processing event");
+            Error::NotAPost => {
+                info!("Markdown file has no front matter, so it is not a post");
+            },
+            Error::DuplicateId(path, other_file_name) => {
+                warn!(
+                    path = %path.to_string_lossy(),
+                    other_file_name = %other_file_name,
+                    "Another post file has the same ID, so this one will be ignored"
+                );
+            },
+            Error::Parsing(path, err) => match err.diagnostic() {
+                Some(diagnostic) => {
+                    log_diagnostic(path, diagnostic);
+                    warn!(path = %path.to_string_lossy(), "Post will not be shown");
+                },
+                None => warn!(error = %err, "Parsing error while processing event"),
             },
         }
     }
 }
+
+/// Logs a problem found in a post file, with its position in the file as separate fields.
+fn log_diagnostic(path: &Path, diagnostic: &Diagnostic) {
+    let path = path.to_string_lossy();
+
+    match diagnostic.severity() {
+        Severity::Error => error!(
+            path = %path,
+            line = diagnostic.line(),
+            column = diagnostic.column(),
+            snippet = diagnostic.snippet(),
+            hint = diagnostic.hint(),
+            "Error in post: {}", diagnostic.message()
+        ),
+        Severity::Warning => warn!(
+            path = %path,
+            line = diagnostic.line(),
+            column = diagnostic.column(),
+            snippet = diagnostic.snippet(),
+            hint = diagnostic.hint(),
+            "Problem in post: {}", diagnostic.message()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, sync::Arc};
+
+    use chrono::{DateTime, Duration, SecondsFormat, Utc};
+
+    use blog::{
+        codeblock::CodeBlockRenderer,
+        db::ConcurrentPostsStore,
+        shortcode::ShortcodeRegistry,
+        tag::TagRegistry,
+    };
+
+    use crate::{config::tests::config, test_util::{toml_header, TempDir}, Context};
+
+    use super::{EventTarget, Renderer};
+
+    fn write_post(dir: &Path, id: &str, extra: &str, body: &str) {
+        fs::write(dir.join(format!("{}.toml.md", id)), format!("{}\n---\n{}\n", toml_header(extra), body)).unwrap();
+    }
+
+    fn published(time: DateTime<Utc>) -> String {
+        format!("published = \"{}\"", time.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+
+    #[test]
+    fn scheduled_post_links_to_posts_published_by_its_publish_date() {
+        let dir = TempDir::new("scheduled_post_links");
+        let now = "2022-06-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        write_post(dir.path(), "draft", "draft = true", "Draft.");
+        write_post(dir.path(), "earlier", &published(now + Duration::hours(1)), "Earlier.");
+        write_post(dir.path(), "later", &published(now + Duration::hours(3)), "Later.");
+        write_post(dir.path(), "scheduled", &published(now + Duration::hours(2)), "[[draft]] [[earlier]] [[later]]");
+
+        let context = Arc::new(Context::new(config(dir.path(), ""), ConcurrentPostsStore::new()));
+        let (renderer, _tx) = Renderer::new(
+            context.clone(),
+            CodeBlockRenderer::new(),
+            ShortcodeRegistry::new(),
+            TagRegistry::new(),
+            dir.path().to_owned()
+        );
+
+        let assert_links = |html: &str| {
+            assert!(!html.contains("href=\"/articles/draft\""));
+            assert!(html.contains("href=\"/articles/earlier\""));
+            assert!(!html.contains("href=\"/articles/later\""));
+            assert_eq!(html.matches("class=\"broken_link\"").count(), 2);
+        };
+
+        renderer.scan(now);
+        let html = context.posts().read_blocking().get_scheduled("scheduled").unwrap().html().0.to_owned();
+        assert_links(&html);
+
+        renderer.publish_scheduled(now + Duration::hours(2));
+        assert!(context.posts().read_blocking().get_scheduled("scheduled").is_none());
+        let html = context.posts().read_blocking().get("scheduled").unwrap().html().0.to_owned();
+        assert_links(&html);
+
+        // Posts which are rendered again after being published stay published.
+        let target = EventTarget::from_path(dir.path().join("scheduled.toml.md")).unwrap();
+        renderer.update(&target, now + Duration::hours(2));
+        assert!(context.posts().read_blocking().get_scheduled("scheduled").is_none());
+    }
+}
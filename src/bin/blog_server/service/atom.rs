@@ -1,15 +1,17 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use atom_syndication as atom;
 use axum::{body::Bytes, extract::Extension};
 
 use blog::time::unix_epoch;
 
-use crate::Context;
+use crate::{Context, url};
 
-use super::response::Atom;
+use super::{feed_ext, response::Atom};
 
 pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Atom<Bytes> {
+    let site = &context.config().site;
+
     let (atom_entries, updated) = {
         let guard = context.posts().read().await;
 
@@ -17,25 +19,46 @@ pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Atom<
             .iter_by_published()
             .take(context.config().atom.num_posts)
             .map(|post| {
+                let mut links = vec![atom::LinkBuilder::default()
+                    .href(url::post_canonical(site, post))
+                    .rel("alternate".to_owned())
+                    .mime_type(Some("text/html".to_owned()))
+                    .hreflang(post.lang().map(str::to_owned))
+                    .build()];
+
+                if let Some(cover) = post.cover() {
+                    links.push(atom::LinkBuilder::default()
+                        .href(site.url(&url::post_media(cover.path())))
+                        .rel("enclosure".to_owned())
+                        .mime_type(cover.mime_type().map(str::to_owned))
+                        .title(Some(cover.alt().to_owned()))
+                        .build());
+                }
+
                 atom::EntryBuilder::default()
                     .id(format!("urn:uuid:{}", post.uuid()))
-                    .title(post.title().to_owned())
+                    .title(atom::Text {
+                        lang: post.lang().map(str::to_owned),
+                        ..atom::Text::plain(post.title().to_owned())
+                    })
+                    .summary(post.summary().map(|summary| atom::Text {
+                        lang: post.lang().map(str::to_owned),
+                        ..atom::Text::plain(summary.to_owned())
+                    }))
+                    .published(Some(post.published().into()))
                     .updated(post.updated())
-                    .links(vec![atom::LinkBuilder::default()
-                        .href(format!(
-                            "{}://{}/articles/{}",
-                            context.config().site.protocol,
-                            context.config().site.domain,
-                            post.id()
-                        ))
-                        .rel("alternate".to_owned())
-                        .mime_type(Some("text/html".to_owned()))
-                        .build()])
-                    .author(
-                        atom::PersonBuilder::default()
-                            .name(post.author().to_owned())
-                            .build(),
-                    )
+                    .links(links)
+                    .authors(post.authors()
+                        .iter()
+                        .map(|author| atom_person(&context, author))
+                        .collect::<Vec<_>>())
+                    .extensions(feed_ext::reading_stats(context.config(), post, |name, value| {
+                        atom::extension::Extension {
+                            name,
+                            value: Some(value),
+                            ..atom::extension::Extension::default()
+                        }
+                    }))
                     .build()
             })
             .collect::<Vec<atom::Entry>>();
@@ -49,20 +72,19 @@ pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Atom<
         atom::FeedBuilder::default()
             .id(format!("urn:uuid:{}", context.config().namespace_uuid))
             .title(context.config().atom.title.clone())
+            .namespaces(BTreeMap::from([(
+                feed_ext::PREFIX.to_owned(),
+                feed_ext::NAMESPACE.to_owned(),
+            )]))
+            .lang(Some(site.lang.clone()))
             .updated(updated)
             .links(vec![
                 atom::LinkBuilder::default()
-                    .href(format!(
-                        "{}://{}/atom.xml",
-                        context.config().site.protocol, context.config().site.domain
-                    ))
+                    .href(site.url("/atom.xml"))
                     .rel("self".to_owned())
                     .build(),
                 atom::LinkBuilder::default()
-                    .href(format!(
-                        "{}://{}/articles/",
-                        context.config().site.protocol, context.config().site.domain
-                    ))
+                    .href(site.url("/articles/"))
                     .rel("alternate".to_owned())
                     .mime_type(Some("text/html".to_owned()))
                     .build(),
@@ -73,3 +95,17 @@ pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Atom<
             .into(),
     )
 }
+
+/// Creates an Atom person for a post author. Authors in the author registry are linked to their
+/// author page; other authors only have a name.
+fn atom_person(context: &Context, author: &str) -> atom::Person {
+    match context.config().author(author) {
+        Some(author) => atom::PersonBuilder::default()
+            .name(author.name.clone())
+            .uri(Some(context.config().site.url(&format!("/authors/{}", url::path_segment(&author.key)))))
+            .build(),
+        None => atom::PersonBuilder::default()
+            .name(author.to_owned())
+            .build(),
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use maud::html;
+
+use crate::{Context, template};
+
+use super::response::{Error, Html};
+
+pub(super) async fn handle(
+    Path(author_key): Path<String>,
+    Extension(context): Extension<Arc<Context>>,
+) -> Result<Html, Error>
+{
+    let author = context.config().author(&author_key)
+        .ok_or(Error::AuthorNotFound)?;
+
+    let guard = context.posts().read().await;
+
+    Ok(Html::new()
+        .with_lang(context.config().site.lang.clone())
+        .with_title_owned(author.name.clone())
+        .with_crawler_permissive()
+        .with_head(html! {
+            link href="/static/styles/main.css" rel="stylesheet";
+            link rel="alternate" type="application/atom+xml" href="/atom.xml";
+            link rel="alternate" type="application/rss+xml" href="/rss.xml";
+        })
+        .with_body(template::main_page(html! {
+            section .content_section .author_profile {
+                @if let Some(avatar) = author.avatar.as_deref() {
+                    img .author_avatar src=(avatar) alt={"Avatar of " (author.name)} width="128";
+                }
+                h1 { (author.name) }
+                @if let Some(bio) = author.bio.as_deref() {
+                    p .author_bio { (bio) }
+                }
+                @if author.url.is_some() || !author.rel_me.is_empty() {
+                    ul .author_links {
+                        @if let Some(url) = author.url.as_deref() {
+                            li { a href=(url) { (url) } }
+                        }
+                        @for url in &author.rel_me {
+                            li { a rel="me" href=(url) { (url) } }
+                        }
+                    }
+                }
+            }
+
+            section .content_section {
+                h2 { "Articles" }
+                ul .articles_list {
+                    @if let Some(posts) = guard.iter_by_author(&author.key) {
+                        @for post in posts.rev() {
+                            (template::post_list_item(context.config(), post))
+                        }
+                    }
+                }
+            }
+        })))
+}
@@ -9,6 +9,7 @@ use super::response::Html;
 
 pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Html {
     Html::new()
+        .with_lang(context.config().site.lang.clone())
         .with_title_static("Contact")
         .with_crawler_permissive()
         .with_head(html! {
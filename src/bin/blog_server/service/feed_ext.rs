@@ -0,0 +1,39 @@
+use std::collections::BTreeMap;
+
+use blog::post::Post;
+
+use crate::Config;
+
+/// The prefix of the feed elements giving a post's word count and estimated reading time, which
+/// neither Atom nor RSS have their own elements for.
+pub(super) const PREFIX: &str = "blog";
+
+/// The namespace of the feed elements with the prefix `PREFIX`. This only identifies the
+/// elements, so it is fixed rather than depending on the site's URL.
+pub(super) const NAMESPACE: &str = "https://github.com/pantonshire/blog_server#feed-extensions";
+
+/// Creates the extension elements giving a post's word count and estimated reading time in
+/// minutes. The Atom and RSS crates each have their own extension type, so `extension` creates an
+/// element from its qualified name and its value.
+pub(super) fn reading_stats<E, F>(
+    config: &Config,
+    post: &Post,
+    extension: F,
+) -> BTreeMap<String, BTreeMap<String, Vec<E>>>
+where
+    F: Fn(String, String) -> E,
+{
+    let elements = [
+        ("wordCount", post.word_count().to_string()),
+        ("readingTime", post.reading_time_mins(config.reading.words_per_minute).to_string()),
+    ];
+
+    let elements = elements
+        .into_iter()
+        .map(|(name, value)| {
+            (name.to_owned(), vec![extension(format!("{}:{}", PREFIX, name), value)])
+        })
+        .collect();
+
+    BTreeMap::from([(PREFIX.to_owned(), elements)])
+}
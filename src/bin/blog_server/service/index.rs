@@ -3,12 +3,20 @@ use std::sync::Arc;
 use axum::extract::Extension;
 use maud::html;
 
-use crate::{Context, template};
+use blog::{db::PostsStore, post::Post};
+
+use crate::{config::HomepageConfig, Context, template};
 
 use super::response::Html;
 
 pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Html {
+    let (pinned_posts, recent_posts) = {
+        let guard = context.posts().read().await;
+        homepage_posts(&guard, &context.config().homepage)
+    };
+
     Html::new()
+        .with_lang(context.config().site.lang.clone())
         .with_title_static("Pantonshire")
         .with_crawler_permissive()
         .with_head(html! {
@@ -47,17 +55,19 @@ pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Html
 
             section .content_section {
                 h2 { "Articles" }
-                p { "Some recent ones:" }
-                ul .articles_list {
-                    @for post in context.posts().read().await.iter_by_published().rev().take(3) {
-                        li {
-                            h3 { a href={"/articles/" (post.id())} { (post.title()) } }
-                            @if let Some(subtitle) = post.subtitle() {
-                                p .article_list_subtitle { (subtitle) }
-                            }
-                            p .article_list_published_date {
-                                "Published " (post.published().format("%Y/%m/%d"))
-                            }
+                @if !pinned_posts.is_empty() {
+                    p { "Some favourites:" }
+                    ul .articles_list {
+                        @for post in &pinned_posts {
+                            (template::post_list_item(context.config(), post))
+                        }
+                    }
+                }
+                @if !recent_posts.is_empty() {
+                    p { "Some recent ones:" }
+                    ul .articles_list {
+                        @for post in &recent_posts {
+                            (template::post_list_item(context.config(), post))
                         }
                     }
                 }
@@ -67,3 +77,83 @@ pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Html
             }
         }))
 }
+
+/// Returns the pinned posts and the recent posts to show on the homepage, newest first.
+fn homepage_posts(posts: &PostsStore, config: &HomepageConfig) -> (Vec<Arc<Post>>, Vec<Arc<Post>>) {
+    let pinned_posts = posts.iter_pinned()
+        .rev()
+        .take(config.pinned_posts)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // Leave out the pinned posts which are already shown, so that no post is listed twice.
+    let recent_posts = posts.iter_by_published()
+        .rev()
+        .filter(|post| !pinned_posts.iter().any(|pinned| pinned.id() == post.id()))
+        .take(config.recent_posts)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    (pinned_posts, recent_posts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+
+    use blog::{db::PostsStore, post::Post};
+
+    use crate::{config::HomepageConfig, service::response::tests::post};
+
+    use super::homepage_posts;
+
+    /// Returns a store with five posts, published a day apart in order of ID, of which `b` and
+    /// `d` are pinned.
+    fn store() -> PostsStore {
+        let mut store = PostsStore::new();
+        for (i, id) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+            let pinned = if id == "b" || id == "d" { "pinned = true\n" } else { "" };
+            let extra = format!("{}published = \"2022-01-0{}T00:00:00Z\"", pinned, i + 1);
+            store.insert(post(id, &extra), Utc::now());
+        }
+        store
+    }
+
+    fn homepage(pinned_posts: usize, recent_posts: usize) -> (Vec<String>, Vec<String>) {
+        let ids = |posts: Vec<Arc<Post>>| posts.iter().map(|post| post.id().to_string()).collect();
+        let (pinned, recent) = homepage_posts(&store(), &HomepageConfig { pinned_posts, recent_posts });
+        (ids(pinned), ids(recent))
+    }
+
+    #[test]
+    fn pinned_posts_are_not_listed_again_as_recent() {
+        let (pinned, recent) = homepage(3, 3);
+        assert_eq!(pinned, ["d", "b"]);
+        assert_eq!(recent, ["e", "c", "a"]);
+
+        // Pinned posts which are not shown as pinned are still recent.
+        let (pinned, recent) = homepage(1, 3);
+        assert_eq!(pinned, ["d"]);
+        assert_eq!(recent, ["e", "c", "b"]);
+    }
+
+    #[test]
+    fn no_pinned_posts() {
+        let (pinned, recent) = homepage(0, 3);
+        assert!(pinned.is_empty());
+        assert_eq!(recent, ["e", "d", "c"]);
+    }
+
+    #[test]
+    fn no_recent_posts() {
+        let (pinned, recent) = homepage(3, 0);
+        assert_eq!(pinned, ["d", "b"]);
+        assert!(recent.is_empty());
+
+        let (pinned, recent) = homepage(0, 0);
+        assert!(pinned.is_empty());
+        assert!(recent.is_empty());
+    }
+}
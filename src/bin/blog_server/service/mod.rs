@@ -1,11 +1,15 @@
 mod atom;
+mod author;
 mod contact;
+mod feed_ext;
 mod index;
 mod post;
 mod posts_list;
 mod response;
 mod rss;
+mod series;
 mod site;
 mod static_content;
+mod tag;
 
 pub(crate) use site::service as site_service;
@@ -1,47 +1,281 @@
 use std::sync::Arc;
 
-use axum::extract::{Extension, Path};
-use maud::html;
+use axum::{
+    extract::{Extension, Path},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use maud::{html, Markup};
 
-use crate::{Context, template};
+use blog::{db::PostsStore, post::{article_path, Post, TocEntry}};
+use libshire::strings::ShString22;
 
-use super::response::{Error, Html};
+use crate::{Context, template, url};
+
+use super::response::{CrawlerHints, Error, Html, PermanentRedirect};
 
 pub(super) async fn handle(
     Path(post_id): Path<String>,
     Extension(context): Extension<Arc<Context>>,
+) -> Result<Response, Error>
+{
+    let (post, series_parts, tags) = {
+        let guard = context.posts().read().await;
+
+        let post = match guard.get(&post_id) {
+            Some(post) => post.clone(),
+            None => {
+                // If there is no post with the requested ID, it may be an old ID of a post that
+                // has since been renamed, in which case we redirect to the post's new URL.
+                let new_id = guard.resolve_alias(&post_id)
+                    .ok_or(Error::PostNotFound)?;
+
+                return Ok(PermanentRedirect(article_path(new_id))
+                    .into_response());
+            },
+        };
+
+        let series_parts = series_parts(&guard, &post);
+        let tags = guard.post_tags(&post);
+
+        (post, series_parts, tags)
+    };
+
+    Ok(render_post(&context, &post, &series_parts, &tags)
+        .with_crawler_hints(CrawlerHints::for_post(&post))
+        .into_response())
+}
+
+/// Handler for previewing posts which have not been published yet. The page is served with the
+/// default restrictive crawler hints, so that search engines do not index unpublished content.
+pub(super) async fn handle_preview(
+    Path(post_id): Path<String>,
+    Extension(context): Extension<Arc<Context>>,
 ) -> Result<Html, Error>
 {
-    let post = context.posts().get(&post_id)
-        .await
-        .ok_or(Error::PostNotFound)?;
+    let (post, series_parts, tags) = {
+        let guard = context.posts().read().await;
+
+        let post = guard.get_unpublished(&post_id)
+            .ok_or(Error::PostNotFound)?
+            .clone();
+
+        let series_parts = series_parts(&guard, &post);
+        let tags = guard.post_tags(&post);
+
+        (post, series_parts, tags)
+    };
+
+    Ok(render_post(&context, &post, &series_parts, &tags))
+}
 
-    Ok(Html::new()
-        .with_crawler_permissive()
+/// Returns the published posts in the same series as the given post, in series order. If the post
+/// is not part of a series, the returned `Vec` is empty.
+fn series_parts(posts: &PostsStore, post: &Post) -> Vec<Arc<Post>> {
+    post.series()
+        .and_then(|series| posts.iter_series(series.name()))
+        .map(|parts| parts.cloned().collect())
+        .unwrap_or_default()
+}
+
+fn render_post(context: &Context, post: &Post, series_parts: &[Arc<Post>], tags: &[ShString22]) -> Html {
+    // Show the table of contents if the post's header asks for it, or otherwise if the post is
+    // long enough to benefit from one.
+    let show_toc = !post.toc().is_empty() && post.header()
+        .toc()
+        .unwrap_or(post.word_count() >= context.config().toc.min_words);
+
+    let canonical_url = url::post_canonical(&context.config().site, post);
+
+    Html::new()
+        .with_lang(post.lang().unwrap_or(&context.config().site.lang).to_owned())
         .with_title_owned(post.title().to_owned())
         .with_head(html! {
+            @if let Some(summary) = post.summary() {
+                meta name="description" content=(summary);
+            }
+            meta property="og:type" content="article";
+            meta property="og:title" content=(post.title());
+            link rel="canonical" href=(canonical_url);
+            meta property="og:url" content=(canonical_url);
+            @if let Some(summary) = post.summary() {
+                meta property="og:description" content=(summary);
+            }
+            @if let Some(cover) = post.cover() {
+                meta property="og:image" content=(context.config().site.url(&url::post_media(cover.path())));
+                meta property="og:image:alt" content=(cover.alt());
+            }
             link href="/static/styles/main.css" rel="stylesheet";
             link href="/static/styles/code.css" rel="stylesheet";
+            @for style in post.styles() {
+                link href=(url::post_asset(style)) rel="stylesheet";
+            }
+            @for script in post.scripts() {
+                script src=(url::post_asset(script)) defer {}
+            }
             link rel="alternate" type="application/atom+xml" href="/atom.xml";
             link rel="alternate" type="application/rss+xml" href="/rss.xml";
         })
-        .with_body(template::main_page(html! {
+        .with_body(template::main_page_with_layout(post.layout(), html! {
+            @if let Some(cover) = post.cover() {
+                figure .article_cover {
+                    img src=(url::post_media(cover.path())) alt=(cover.alt());
+                    @if let Some(caption) = cover.caption() {
+                        figcaption .quiet { (caption) }
+                    }
+                }
+            }
             section .article_header {
+                @if post.draft() {
+                    p .article_preview_notice { "Draft preview: this article has not been published" }
+                } @else if post.published() > Utc::now() {
+                    p .article_preview_notice {
+                        "Scheduled preview: this article will be published on "
+                        (post.published().format("%Y/%m/%d at %H:%M UTC"))
+                    }
+                } @else if post.unlisted() {
+                    p .article_preview_notice { "Unlisted: this article is only visible to people with the link" }
+                }
                 h1 .article_title { (post.title()) }
                 @if let Some(subtitle) = post.subtitle() {
                     p .article_subtitle { (subtitle) }
                 }
+                @if !post.authors().is_empty() {
+                    p .article_authors {
+                        "By " (template::post_authors(context.config(), post.authors()))
+                    }
+                }
                 p .article_published_date { "Published " (post.published().format("%Y/%m/%d")) }
-                @if let Some(source_url) = context.config().github.edit_url.as_deref() {
+                p .article_reading_time {
+                    (post.word_count()) " words"
+                    @if post.code_word_count() > 0 {
+                        " (plus " (post.code_word_count()) " words of code)"
+                    }
+                    " · " (template::reading_time(context.config(), post))
+                }
+                @if let Some(updated) = post.header().updated() {
+                    p .article_updated_date { "Updated " (updated.format("%Y/%m/%d")) }
+                }
+                @if !tags.is_empty() {
+                    ul .article_tags {
+                        @for tag in tags {
+                            li {
+                                a href={"/tags/" (url::path_segment(tag))}
+                                    title=[context.config().tag(tag).and_then(|tag| tag.description.as_deref())]
+                                {
+                                    (context.config().tag_label(tag))
+                                }
+                            }
+                        }
+                    }
+                }
+                @if let (Some(source_url), Some(file_name)) = (context.config().github.edit_url.as_deref(), post.file_name()) {
                     p .article_edit {
-                        a href={(source_url) "/" (post.id()) ".toml.md"} {
+                        a href={(source_url) "/" (url::path_segment(file_name))} {
                             "Propose a change on GitHub"
                         }
                     }
                 }
             }
+            @if let Some(series) = post.series() {
+                aside .article_series {
+                    p .article_series_heading {
+                        @if let Some(pos) = series_parts.iter().position(|part| part.id() == post.id()) {
+                            "Part " (pos + 1) " of " (series_parts.len()) " in the series "
+                        } @else {
+                            "Part of the series "
+                        }
+                        a href={"/series/" (url::path_segment(series.name()))} { (series.name()) }
+                    }
+                    ol .article_series_parts {
+                        @for part in series_parts {
+                            li {
+                                @if part.id() == post.id() {
+                                    span .article_series_current { (part.title()) }
+                                } @else {
+                                    a href=(article_path(part.id())) { (part.title()) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            @if show_toc {
+                nav .article_toc {
+                    h2 .article_toc_heading { "Contents" }
+                    (render_toc(post.toc()))
+                }
+            }
             article .article_content {
                 (post.html())
             }
-        })))
+            @if !post.changelog().is_empty() {
+                section .article_changelog {
+                    h2 { "Changelog" }
+                    ul {
+                        @for entry in post.changelog() {
+                            li {
+                                time .article_changelog_date datetime=(entry.date().to_rfc3339()) {
+                                    (entry.date().format("%Y/%m/%d"))
+                                }
+                                " " (entry.note())
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+}
+
+fn render_toc(entries: &[TocEntry]) -> Markup {
+    html! {
+        ol {
+            @for entry in entries {
+                li {
+                    a href={"#" (entry.id())} { (entry.title()) }
+                    @if !entry.children().is_empty() {
+                        (render_toc(entry.children()))
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path as FsPath, sync::Arc};
+
+    use axum::{extract::{Extension, Path}, response::IntoResponse};
+    use chrono::Utc;
+
+    use blog::db::ConcurrentPostsStore;
+
+    use crate::{config::tests::config, service::response::tests::{post, RESTRICTIVE}, Context};
+
+    use super::handle_preview;
+
+    #[test]
+    fn previews_are_always_restrictive() {
+        let posts = ConcurrentPostsStore::new();
+        {
+            let mut guard = posts.write_blocking();
+            let now = Utc::now();
+            guard.insert(post("draft", "draft = true\n[robots]\nindex = true\nfollow = true"), now);
+            guard.insert(post("scheduled", "published = \"2999-01-01T00:00:00Z\"\n[robots]\nindex = true"), now);
+        }
+        let context = Arc::new(Context::new(config(FsPath::new("."), ""), posts));
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        for id in ["draft", "scheduled"] {
+            let html = runtime
+                .block_on(handle_preview(Path(id.to_owned()), Extension(context.clone())))
+                .unwrap();
+            let body = runtime
+                .block_on(hyper::body::to_bytes(html.into_response().into_body()))
+                .unwrap();
+            let body = String::from_utf8(body.to_vec()).unwrap();
+            assert!(body.contains(&format!("<meta name=\"robots\" content=\"{}\">", RESTRICTIVE)), "{}", body);
+        }
+    }
 }
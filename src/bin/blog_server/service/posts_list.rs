@@ -9,6 +9,7 @@ use super::response::Html;
 
 pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Html {
     Html::new()
+        .with_lang(context.config().site.lang.clone())
         .with_title_static("Articles")
         .with_crawler_permissive()
         .with_head(html! {
@@ -24,15 +25,7 @@ pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Html
                 }
                 ul .articles_list {
                     @for post in context.posts().read().await.iter_by_published().rev() {
-                        li {
-                            h3 { a href={"/articles/" (post.id())} { (post.title()) } }
-                            @if let Some(subtitle) = post.subtitle() {
-                                p .article_list_subtitle { (subtitle) }
-                            }
-                            p .article_list_published_date {
-                                "Published " (post.published().format("%Y/%m/%d"))
-                            }
-                        }
+                        (template::post_list_item(context.config(), post))
                     }
                 }
             }
@@ -10,10 +10,15 @@ use axum::{
 };
 use maud::{html, Markup, Render, Escaper, DOCTYPE};
 
+use blog::post::Post;
+
 #[derive(Debug)]
 pub(super) enum Error {
     Internal,
     PostNotFound,
+    SeriesNotFound,
+    AuthorNotFound,
+    TagNotFound,
     StaticResourceNotFound,
     RouteNotFound,
 }
@@ -23,6 +28,9 @@ impl Error {
         match self {
             Error::Internal => StatusCode::INTERNAL_SERVER_ERROR,
             Error::PostNotFound => StatusCode::NOT_FOUND,
+            Error::SeriesNotFound => StatusCode::NOT_FOUND,
+            Error::AuthorNotFound => StatusCode::NOT_FOUND,
+            Error::TagNotFound => StatusCode::NOT_FOUND,
             Error::StaticResourceNotFound => StatusCode::NOT_FOUND,
             Error::RouteNotFound => StatusCode::NOT_FOUND,
         }
@@ -64,6 +72,7 @@ impl IntoResponse for Error {
 
 pub(super) struct Html {
     status: StatusCode,
+    lang: Option<String>,
     title: Cow<'static, str>,
     head: Option<Markup>,
     body: Option<Markup>,
@@ -74,6 +83,7 @@ impl Html {
     pub(super) fn new() -> Self {
         Self {
             status: StatusCode::OK,
+            lang: None,
             title: Cow::Borrowed("untitled"),
             head: None,
             body: None,
@@ -85,6 +95,10 @@ impl Html {
         Self { status, ..self }
     }
 
+    pub(super) fn with_lang(self, lang: String) -> Self {
+        Self { lang: Some(lang), ..self }
+    }
+
     pub(super) fn with_title(self, title: Cow<'static, str>) -> Self {
         Self { title, ..self }
     }
@@ -124,7 +138,7 @@ impl IntoResponse for Html {
     fn into_response(self) -> Response {
         let html_doc = html! {
             (DOCTYPE)
-            html {
+            html lang=[self.lang] {
                 head {
                     meta charset="utf-8";
                     
@@ -185,6 +199,27 @@ impl CrawlerHints {
         }
     }
 
+    /// Returns the crawler hints for a post. Unlisted posts start from the restrictive preset and
+    /// other posts from the permissive one, then any flags set in the post's header are applied.
+    pub(super) fn for_post(post: &Post) -> Self {
+        let defaults = if post.unlisted() {
+            Self::restrictive()
+        } else {
+            Self::permissive()
+        };
+
+        match post.header().robots() {
+            Some(robots) => Self {
+                index: robots.index().unwrap_or(defaults.index),
+                follow: robots.follow().unwrap_or(defaults.follow),
+                archive: robots.archive().unwrap_or(defaults.archive),
+                snippet: robots.snippet().unwrap_or(defaults.snippet),
+                image_index: robots.image_index().unwrap_or(defaults.image_index),
+            },
+            None => defaults,
+        }
+    }
+
     fn index_str(self) -> &'static str {
         if self.index {
             "index"
@@ -247,6 +282,22 @@ impl Render for CrawlerHints {
     }
 }
 
+/// A `301 Moved Permanently` response. This is used rather than axum's `Redirect::permanent`, which
+/// responds with `308 Permanent Redirect`, because 301 is more widely understood by crawlers.
+pub(super) struct PermanentRedirect(pub String);
+
+impl IntoResponse for PermanentRedirect {
+    fn into_response(self) -> Response {
+        match HeaderValue::try_from(self.0) {
+            Ok(location) => {
+                (StatusCode::MOVED_PERMANENTLY, [(header::LOCATION, location)])
+                    .into_response()
+            },
+            Err(_) => Error::Internal.into_response(),
+        }
+    }
+}
+
 pub(super) struct Rss<T>(pub T);
 
 impl<T: Into<Full<Bytes>>> IntoResponse for Rss<T> {
@@ -272,3 +323,67 @@ impl<T: Into<Full<Bytes>>> IntoResponse for Atom<T> {
             .into_response()
     }
 }
+
+#[cfg(test)]
+pub(super) mod tests {
+    use libshire::uuid::Uuid;
+    use maud::Render;
+
+    use blog::{
+        codeblock::CodeBlockRenderer,
+        post::{Id, LinkTargets, Post},
+        shortcode::ShortcodeRegistry,
+    };
+
+    use crate::test_util::toml_header;
+
+    use super::CrawlerHints;
+
+    pub(in crate::service) const RESTRICTIVE: &str = "noindex,nofollow,noarchive,nocache,nosnippet,noimageindex";
+
+    pub(in crate::service) fn post(id: &str, extra: &str) -> Post {
+        Post::new_from_str(
+            &CodeBlockRenderer::new(),
+            &ShortcodeRegistry::new(),
+            &LinkTargets::new(),
+            Uuid::nil(),
+            Id::new(id).unwrap(),
+            None,
+            &format!("{}\n---\nContent.\n", toml_header(extra))
+        ).unwrap()
+    }
+
+    fn hints_for_post(extra: &str) -> String {
+        CrawlerHints::for_post(&post("post", extra)).render().into_string()
+    }
+
+    #[test]
+    fn listed_posts_are_permissive_by_default() {
+        assert_eq!(hints_for_post(""), "index,follow");
+        assert_eq!(hints_for_post("[robots]"), "index,follow");
+    }
+
+    #[test]
+    fn unlisted_posts_are_restrictive_by_default() {
+        assert_eq!(hints_for_post("unlisted = true"), RESTRICTIVE);
+    }
+
+    #[test]
+    fn robots_flags_override_the_defaults() {
+        assert_eq!(hints_for_post("[robots]\nindex = false"), "noindex,follow");
+        assert_eq!(hints_for_post("[robots]\nfollow = false"), "index,nofollow");
+        assert_eq!(hints_for_post("[robots]\nindex = false\nfollow = false"), "noindex,nofollow");
+        assert_eq!(
+            hints_for_post("[robots]\narchive = false\nsnippet = false\nimage_index = false"),
+            "index,follow,noarchive,nocache,nosnippet,noimageindex"
+        );
+        assert_eq!(
+            hints_for_post("unlisted = true\n[robots]\nindex = true"),
+            "index,nofollow,noarchive,nocache,nosnippet,noimageindex"
+        );
+        assert_eq!(
+            hints_for_post("unlisted = true\n[robots]\nfollow = true\nsnippet = true"),
+            "noindex,follow,noarchive,nocache,noimageindex"
+        );
+    }
+}
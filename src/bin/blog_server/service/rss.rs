@@ -4,14 +4,20 @@ use axum::{
     body::Bytes,
     extract::Extension,
 };
+use rss::extension::{
+    dublincore::{self, DublinCoreExtensionBuilder},
+    Extension as RssExtension,
+};
 
 use blog::time::unix_epoch;
 
-use crate::Context;
+use crate::{Context, url};
 
-use super::response::Rss;
+use super::{feed_ext, response::Rss};
 
 pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Rss<Bytes> {
+    let site = &context.config().site;
+
     let (rss_items, updated) = {
         let guard = context.posts().read().await;
 
@@ -20,17 +26,41 @@ pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Rss<B
             .map(|post| {
                 rss::ItemBuilder::default()
                     .title(Some(post.title().to_owned()))
+                    .description(post.summary().map(str::to_owned))
                     .guid(Some(rss::GuidBuilder::default()
                         .value(post.uuid().to_string())
                         .permalink(false)
                         .build()))
-                    .link(Some(format!(
-                        "{}://{}/articles/{}",
-                        context.config().site.protocol,
-                        context.config().site.domain,
-                        post.id()
-                    )))
+                    .link(Some(url::post_canonical(site, post)))
                     .pub_date(Some(post.published().to_rfc2822()))
+                    // RSS's own `author` element must be an email address, so use the Dublin Core
+                    // `creator` element for the authors' names instead.
+                    .dublin_core_ext(Some(DublinCoreExtensionBuilder::default()
+                        .creators(post.authors()
+                            .iter()
+                            .map(|author| context.config()
+                                .author(author)
+                                .map(|author| author.name.clone())
+                                .unwrap_or_else(|| author.to_string()))
+                            .collect::<Vec<_>>())
+                        .languages(post.lang()
+                            .map(|lang| vec![lang.to_owned()])
+                            .unwrap_or_default())
+                        .build()))
+                    // RSS requires the length of an enclosure in bytes. We do not know it without
+                    // reading the file, so use 0 as recommended by the RSS Advisory Board.
+                    .enclosure(post.cover().map(|cover| rss::EnclosureBuilder::default()
+                        .url(site.url(&url::post_media(cover.path())))
+                        .length("0".to_owned())
+                        .mime_type(cover.mime_type().unwrap_or("application/octet-stream").to_owned())
+                        .build()))
+                    .extensions(feed_ext::reading_stats(context.config(), post, |name, value| {
+                        RssExtension {
+                            name,
+                            value: Some(value),
+                            ..RssExtension::default()
+                        }
+                    }))
                     .build()
             })
             .collect::<Vec<rss::Item>>();
@@ -41,16 +71,19 @@ pub(super) async fn handle(Extension(context): Extension<Arc<Context>>) -> Rss<B
         (rss_items, updated)
     };
 
-    Rss(rss::ChannelBuilder::default()
+    let mut channel = rss::ChannelBuilder::default()
         .title(context.config().rss.title.clone())
-        .link(format!(
-            "{}://{}",
-            context.config().site.protocol, context.config().site.domain
-        ))
+        .link(site.url(""))
+        .language(Some(site.lang.clone()))
         .ttl(Some(context.config().rss.ttl.to_string()))
         .last_build_date(Some(updated.to_rfc2822()))
         .items(rss_items)
-        .build()
+        .build();
+
+    channel.namespaces.insert("dc".to_owned(), dublincore::NAMESPACE.to_owned());
+    channel.namespaces.insert(feed_ext::PREFIX.to_owned(), feed_ext::NAMESPACE.to_owned());
+
+    Rss(channel
         .to_string()
         .into())
 }
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use maud::html;
+
+use crate::{Context, template};
+
+use super::response::{Error, Html};
+
+pub(super) async fn handle(
+    Path(series_name): Path<String>,
+    Extension(context): Extension<Arc<Context>>,
+) -> Result<Html, Error>
+{
+    let guard = context.posts().read().await;
+
+    let posts = guard.iter_series(&series_name)
+        .ok_or(Error::SeriesNotFound)?;
+
+    Ok(Html::new()
+        .with_lang(context.config().site.lang.clone())
+        .with_title_owned(series_name.clone())
+        .with_crawler_permissive()
+        .with_head(html! {
+            link href="/static/styles/main.css" rel="stylesheet";
+            link rel="alternate" type="application/atom+xml" href="/atom.xml";
+            link rel="alternate" type="application/rss+xml" href="/rss.xml";
+        })
+        .with_body(template::main_page(html! {
+            section .content_section {
+                h1 { (series_name) }
+                p {
+                    "A series of " (posts.len()) " articles, in order."
+                }
+                ol .articles_list {
+                    @for post in posts {
+                        (template::post_list_item(context.config(), post))
+                    }
+                }
+            }
+        })))
+}
@@ -15,13 +15,16 @@ use crate::Context;
 
 use super::{
     atom,
+    author,
     contact,
     index,
     post,
     posts_list,
     response::Error,
     rss,
+    series,
     static_content,
+    tag,
 };
 
 pub(crate) fn service(context: Arc<Context>) -> Router {
@@ -32,6 +35,10 @@ pub(crate) fn service(context: Arc<Context>) -> Router {
         .route("/rss.xml", get(rss::handle))
         .route("/atom.xml", get(atom::handle))
         .route("/articles/:post_id", get(post::handle))
+        .route("/preview/:post_id", get(post::handle_preview))
+        .route("/series/:series_name", get(series::handle))
+        .route("/authors/:author_key", get(author::handle))
+        .route("/tags/:tag", get(tag::handle))
         .route("/robots.txt", static_content::file_service(&context.config().content.robots_path, None))
         .route("/favicon.ico", static_content::file_service(&context.config().content.favicon_dir.join("favicon.ico"), None))
         .route("/favicon-16x16.png", static_content::file_service(&context.config().content.favicon_dir.join("favicon-16x16.png"), None))
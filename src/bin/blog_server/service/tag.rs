@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use maud::html;
+
+use crate::{Context, template};
+
+use super::response::{Error, Html};
+
+pub(super) async fn handle(
+    Path(tag): Path<String>,
+    Extension(context): Extension<Arc<Context>>,
+) -> Result<Html, Error>
+{
+    let guard = context.posts().read().await;
+
+    // Use the canonical name of the tag, in case the tag in the URL is an alias or is written
+    // differently.
+    let tag = guard.resolve_tag(&tag)
+        .ok_or(Error::TagNotFound)?;
+
+    let posts = guard.iter_by_tag(&tag)
+        .ok_or(Error::TagNotFound)?;
+
+    let config = context.config().tag(&tag);
+    let label = context.config().tag_label(&tag);
+
+    Ok(Html::new()
+        .with_lang(context.config().site.lang.clone())
+        .with_title_owned(format!("Articles tagged {}", label))
+        .with_crawler_permissive()
+        .with_head(html! {
+            link href="/static/styles/main.css" rel="stylesheet";
+            link rel="alternate" type="application/atom+xml" href="/atom.xml";
+            link rel="alternate" type="application/rss+xml" href="/rss.xml";
+        })
+        .with_body(template::main_page(html! {
+            section .content_section {
+                h1 { "Articles tagged " (label) }
+                @if let Some(description) = config.and_then(|tag| tag.description.as_deref()) {
+                    p { (description) }
+                }
+                ul .articles_list {
+                    @for post in posts.rev() {
+                        (template::post_list_item(context.config(), post))
+                    }
+                }
+            }
+        })))
+}
@@ -1,6 +1,22 @@
 use maud::{html, Markup};
 
+use blog::post::{article_path, Layout, Post};
+use libshire::strings::ShString22;
+
+use crate::{Config, url};
+
 pub(crate) fn main_page(content: Markup) -> Markup {
+    main_page_with_layout(Layout::Standard, content)
+}
+
+/// Renders the page header, main content and footer, with the content laid out according to the
+/// given layout.
+pub(crate) fn main_page_with_layout(layout: Layout, content: Markup) -> Markup {
+    let content_class = match layout {
+        Layout::Standard => None,
+        Layout::Wide => Some("layout_wide"),
+    };
+
     html! {
         header #page_header {
             nav #page_nav {
@@ -20,7 +36,7 @@ pub(crate) fn main_page(content: Markup) -> Markup {
         }
 
         main #page_main {
-            #content {
+            #content class=[content_class] {
                 (content)
             }
         }
@@ -46,3 +62,81 @@ pub(crate) fn main_page(content: Markup) -> Markup {
         }
     }
 }
+
+pub(crate) fn post_list_item(config: &Config, post: &Post) -> Markup {
+    html! {
+        li lang=[post.lang()] {
+            @if let Some(cover) = post.cover() {
+                a href=(article_path(post.id())) {
+                    img .article_list_thumbnail
+                        src=(url::post_media(cover.path()))
+                        alt=(cover.alt())
+                        loading="lazy";
+                }
+            }
+            h3 { a href=(article_path(post.id())) hreflang=[post.lang()] { (post.title()) } }
+            @if let Some(subtitle) = post.subtitle() {
+                p .article_list_subtitle { (subtitle) }
+            }
+            @if let Some(summary) = post.summary() {
+                p .article_list_summary { (summary) }
+            }
+            p .article_list_published_date {
+                "Published " (post.published().format("%Y/%m/%d"))
+                " · " (reading_time(config, post))
+            }
+        }
+    }
+}
+
+/// Renders a list of post authors, linking to the author page of each author who is in the
+/// author registry.
+pub(crate) fn post_authors(config: &Config, authors: &[ShString22]) -> Markup {
+    html! {
+        @for (i, author) in authors.iter().enumerate() {
+            @if i > 0 {
+                @if i + 1 == authors.len() { " and " } @else { ", " }
+            }
+            @if let Some(author) = config.author(author) {
+                a href={"/authors/" (url::path_segment(&author.key))} { (author.name) }
+            } @else {
+                (author)
+            }
+        }
+    }
+}
+
+/// Returns the estimated time to read a post, for example "5 min read".
+pub(crate) fn reading_time(config: &Config, post: &Post) -> String {
+    format!("{} min read", post.reading_time_mins(config.reading.words_per_minute))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use libshire::strings::ShString22;
+
+    use crate::config::tests::config;
+
+    use super::post_authors;
+
+    fn authors(keys: &[&str]) -> String {
+        let config = config(Path::new("."), "[[author]]\nkey = \"ann\"\nname = \"Ann\"\n");
+        let keys = keys.iter().map(|key| ShString22::new_from_str(key)).collect::<Vec<_>>();
+        post_authors(&config, &keys).into_string()
+    }
+
+    #[test]
+    fn known_authors_link_to_their_pages() {
+        assert_eq!(authors(&["ann"]), "<a href=\"/authors/ann\">Ann</a>");
+        assert_eq!(authors(&[]), "");
+    }
+
+    #[test]
+    fn unknown_authors_are_shown_as_written() {
+        assert_eq!(authors(&["<carol>"]), "&lt;carol&gt;");
+        assert_eq!(authors(&["carol", "ann"]), "carol and <a href=\"/authors/ann\">Ann</a>");
+        assert_eq!(authors(&["ann", "bob", "carol"]), "<a href=\"/authors/ann\">Ann</a>, bob and carol");
+    }
+}
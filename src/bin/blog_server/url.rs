@@ -0,0 +1,46 @@
+use blog::post::{article_path, Asset, AssetRoot, Post};
+
+use crate::config::SiteConfig;
+
+pub(crate) use blog::post::path_segment;
+
+/// Returns the URL path for a file in the post media directory, given its path relative to the
+/// post media directory.
+pub(crate) fn post_media(path: &str) -> String {
+    dir_file("/article_media", path)
+}
+
+/// Returns the URL path for a file in the static content directory, given its path relative to
+/// the static content directory.
+pub(crate) fn static_file(path: &str) -> String {
+    dir_file("/static", path)
+}
+
+/// Returns the URL path for an extra stylesheet or script used by a post.
+pub(crate) fn post_asset(asset: &Asset) -> String {
+    match asset.root() {
+        AssetRoot::Media => post_media(asset.path()),
+        AssetRoot::Static => static_file(asset.path()),
+    }
+}
+
+/// Returns the URL path for a file in the directory served at `prefix`. Paths with `.` or `..`
+/// segments are rejected when the post header is parsed, but they are also skipped here, since
+/// `.` is left unencoded and so `..` would otherwise climb out of the directory.
+fn dir_file(prefix: &str, path: &str) -> String {
+    let mut url = String::from(prefix);
+    for segment in path.split('/').filter(|segment| !matches!(*segment, "" | "." | "..")) {
+        url.push('/');
+        url.extend(path_segment(segment));
+    }
+    url
+}
+
+/// Returns the canonical URL of a post, which is the URL given in its header if it was first
+/// published elsewhere, or otherwise its URL on this site.
+pub(crate) fn post_canonical(site: &SiteConfig, post: &Post) -> String {
+    match post.header().canonical_url() {
+        Some(canonical_url) => canonical_url.to_owned(),
+        None => site.url(&article_path(post.id())),
+    }
+}
@@ -1,15 +1,81 @@
-use std::{env, fs, process};
+use std::{
+    collections::HashMap,
+    env,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use libshire::uuid::Uuid;
 
-use blog::post::PostSource;
+use blog::{
+    codeblock::CodeBlockRenderer,
+    post::{Id, LinkTargets, Post, PostSource, Severity},
+    shortcode::ShortcodeRegistry,
+};
 
+/// Sets the publish date of each of the post files given as arguments, and reports any problems
+/// found in them. Links to post media and static files are checked if the directories they are
+/// served from are given with `--media-dir` and `--static-dir`, and a notice is printed for each
+/// directory which is not; links to other posts are checked against the post files in the same
+/// directory. Exits with a non-zero status if any post could not be read, parsed or written.
+///
+/// Problems such as links to posts or files which do not exist are only reported, unless
+/// `--strict` is given, in which case posts with errors are not given a publish date and the exit
+/// status is non-zero.
 fn main() {
     let mut failed = false;
 
-    let paths = env::args_os()
-        .skip(1);
-    
+    let mut paths = Vec::new();
+    let mut media_dir = None;
+    let mut static_dir = None;
+    let mut strict = false;
+
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        let dir = if arg == "--strict" {
+            strict = true;
+            continue
+        } else if arg == "--media-dir" {
+            &mut media_dir
+        } else if arg == "--static-dir" {
+            &mut static_dir
+        } else {
+            paths.push(arg);
+            continue
+        };
+
+        match args.next() {
+            Some(value) => *dir = Some(PathBuf::from(value)),
+            None => {
+                eprintln!("missing directory after {}", arg.to_string_lossy());
+                process::exit(1);
+            },
+        }
+    }
+
+    let code_renderer = CodeBlockRenderer::new();
+    let shortcodes = ShortcodeRegistry::new();
+
+    let mut base_link_targets = LinkTargets::new();
+    match media_dir {
+        Some(media_dir) => base_link_targets = base_link_targets.with_media_dir(media_dir),
+        None => eprintln!("note: --media-dir was not given, so links to post media will not be checked"),
+    }
+    match static_dir {
+        Some(static_dir) => base_link_targets = base_link_targets.with_static_dir(static_dir),
+        None => eprintln!("note: --static-dir was not given, so links to static files will not be checked"),
+    }
+
+    // The post files in each directory, which are only read once however many different sets of
+    // link targets are made from them.
+    let mut dir_posts = HashMap::<PathBuf, Vec<(Id, PostSource)>>::new();
+    // The posts which can be linked to, keyed by the directory containing them and the time they
+    // must be published by, if any.
+    let mut dir_link_targets = HashMap::<(PathBuf, Option<DateTime<Utc>>), LinkTargets>::new();
+    let now = Utc::now();
+
     for path in paths {
         let contents = match fs::read_to_string(&path) {
             Ok(contents) => contents,
@@ -23,20 +89,78 @@ fn main() {
         let mut source = match contents.parse::<PostSource>() {
             Ok(source) => source,
             Err(err) => {
-                eprintln!("failed to parse {}: {}", path.to_string_lossy(), err);
+                match err.diagnostic() {
+                    Some(diagnostic) => eprintln!("{}", diagnostic.report(&path.to_string_lossy())),
+                    None => eprintln!("failed to parse {}: {}", path.to_string_lossy(), err),
+                }
                 failed = true;
                 continue
             },
         };
 
-        if source.header().published().is_none() {
-            *source.header_mut().published_mut() = Some(Utc::now());
+        for warning in source.warnings() {
+            eprintln!("{}", warning.report(&path.to_string_lossy()));
+        }
+
+        // Render the post to find any problems in its markdown, such as broken links.
+        let id = Path::new(&path)
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(|file_name| PostSource::post_id(file_name, &contents));
+
+        match id {
+            Some(id) => {
+                // Posts can only link to posts which are published by the time they are, but
+                // drafts can link to any post.
+                let published_by = source.header().links_published_by(now);
+                let dir = post_dir(Path::new(&path));
+                let posts = dir_posts
+                    .entry(dir.clone())
+                    .or_insert_with(|| read_posts(&dir));
+                let link_targets = dir_link_targets
+                    .entry((dir, published_by))
+                    .or_insert_with(|| posts_link_targets(posts, published_by, base_link_targets.clone()));
+
+                match Post::new_from_source(&code_renderer, &shortcodes, link_targets, Uuid::nil(), id, None, source.clone()) {
+                    Ok(post) => {
+                        for diagnostic in post.diagnostics() {
+                            eprintln!("{}", diagnostic.report(&path.to_string_lossy()));
+                        }
+
+                        // In strict mode, posts with errors are not given a publish date, so that
+                        // they are not published until the errors are fixed.
+                        if strict && post.diagnostics().iter().any(|diagnostic| diagnostic.severity() == Severity::Error) {
+                            failed = true;
+                            continue
+                        }
+                    },
+                    Err(err) => {
+                        eprintln!("failed to render {}: {}", path.to_string_lossy(), err);
+                        failed = true;
+                        continue
+                    },
+                }
+            },
+            None => {
+                eprintln!("{} is not a post file, so its links were not checked", path.to_string_lossy());
+            },
+        }
+
+        // Drafts should only be given a publish date once they are ready to be published.
+        if source.header().draft() || source.header().published().is_some() {
+            continue;
+        }
+
+        if let Err(err) = source.set_published(now) {
+            eprintln!("failed to set publish date of {}: {}", path.to_string_lossy(), err);
+            failed = true;
+            continue
         }
 
         if let Err(err) = fs::write(&path, source.to_string()) {
             eprintln!("failed to write {}: {}", path.to_string_lossy(), err);
-                failed = true;
-                continue
+            failed = true;
+            continue
         }
     }
 
@@ -44,3 +168,72 @@ fn main() {
         process::exit(1);
     }
 }
+
+/// Returns the directory containing the post file at the given path.
+fn post_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Reads the post files in the given directory. Files which cannot be read or parsed are left
+/// out, since they are only reported if they are being prepublished.
+fn read_posts(dir: &Path) -> Vec<(Id, PostSource)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("failed to read the posts in {}: {}", dir.to_string_lossy(), err);
+            return Vec::new();
+        },
+    };
+
+    let mut posts = Vec::new();
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let file_name = match file_name.to_str() {
+            Some(file_name) => file_name,
+            None => continue,
+        };
+
+        let contents = match fs::read_to_string(entry.path()) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+
+        let id = match PostSource::post_id(file_name, &contents) {
+            Some(id) => id,
+            None => continue,
+        };
+
+        if let Ok(source) = contents.parse::<PostSource>() {
+            posts.push((id, source));
+        }
+    }
+
+    posts
+}
+
+/// Adds the given posts to the link targets, leaving out posts which are not published by
+/// `published_by` if it is given.
+fn posts_link_targets(
+    posts: &[(Id, PostSource)],
+    published_by: Option<DateTime<Utc>>,
+    mut link_targets: LinkTargets
+) -> LinkTargets
+{
+    for (id, source) in posts {
+        let header = source.header();
+        if let Some(published_by) = published_by {
+            if !header.is_published_at(published_by) {
+                continue;
+            }
+        }
+
+        let aliases = header.aliases().iter().map(|alias| &**alias);
+        link_targets.insert(id.clone(), header.title(), aliases);
+    }
+
+    link_targets
+}
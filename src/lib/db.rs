@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeSet, hash_map, HashMap, HashSet},
+    collections::{BTreeSet, hash_map, HashMap},
     iter::FusedIterator,
     sync::Arc,
 };
@@ -8,7 +8,7 @@ use chrono::{DateTime, Utc};
 use libshire::strings::ShString22;
 use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::post::{Post, Id};
+use crate::{post::{Post, Id}, tag::TagRegistry};
 
 pub struct ConcurrentPostsStore {
     inner: RwLock<PostsStore>,
@@ -16,13 +16,21 @@ pub struct ConcurrentPostsStore {
 
 impl ConcurrentPostsStore {
     pub fn new() -> Self {
-        Self { inner: RwLock::new(PostsStore::new()) }
+        Self::with_tags(TagRegistry::new())
+    }
+
+    pub fn with_tags(tags: TagRegistry) -> Self {
+        Self { inner: RwLock::new(PostsStore::with_tags(tags)) }
     }
 
     pub async fn read(&self) -> RwLockReadGuard<'_, PostsStore> {
         self.inner.read().await
     }
 
+    pub fn read_blocking(&self) -> RwLockReadGuard<'_, PostsStore> {
+        self.inner.blocking_read()
+    }
+
     pub fn write_blocking(&self) -> RwLockWriteGuard<'_, PostsStore> {
         self.inner.blocking_write()
     }
@@ -30,6 +38,10 @@ impl ConcurrentPostsStore {
     pub async fn get(&self, id: &str) -> Option<Arc<Post>> {
         self.read().await.get(id).cloned()
     }
+
+    pub async fn get_unpublished(&self, id: &str) -> Option<Arc<Post>> {
+        self.read().await.get_unpublished(id).cloned()
+    }
 }
 
 impl Default for ConcurrentPostsStore {
@@ -41,73 +53,198 @@ impl Default for ConcurrentPostsStore {
 pub struct PostsStore {
     posts: HashMap<Id, Arc<Post>>,
     published_ix: BTreeSet<PublishedIxEntry>,
-    tags_ix: HashMap<ShString22, HashSet<Id>>,
+    pinned_ix: BTreeSet<PublishedIxEntry>,
+    tags_ix: HashMap<ShString22, BTreeSet<PublishedIxEntry>>,
+    tags: TagRegistry,
+    series_ix: HashMap<ShString22, BTreeSet<SeriesIxEntry>>,
+    authors_ix: HashMap<ShString22, BTreeSet<PublishedIxEntry>>,
+    aliases_ix: HashMap<ShString22, BTreeSet<PublishedIxEntry>>,
+    unlisted: HashMap<Id, Arc<Post>>,
+    drafts: HashMap<Id, Arc<Post>>,
+    scheduled: HashMap<Id, Arc<Post>>,
+    scheduled_ix: BTreeSet<PublishedIxEntry>,
 }
 
 // TODO: shrink the various collections on removal to deallocate unneeded space
 
 impl PostsStore {
     pub fn new() -> Self {
+        Self::with_tags(TagRegistry::new())
+    }
+
+    /// Creates an empty store which uses the given registry to resolve tag aliases. Post tags are
+    /// normalised and indexed by their canonical names.
+    pub fn with_tags(tags: TagRegistry) -> Self {
         Self {
             posts: HashMap::new(),
             published_ix: BTreeSet::new(),
+            pinned_ix: BTreeSet::new(),
             tags_ix: HashMap::new(),
+            tags,
+            series_ix: HashMap::new(),
+            authors_ix: HashMap::new(),
+            aliases_ix: HashMap::new(),
+            unlisted: HashMap::new(),
+            drafts: HashMap::new(),
+            scheduled: HashMap::new(),
+            scheduled_ix: BTreeSet::new(),
         }
     }
 
+    /// Returns the published post with the given ID, including unlisted posts.
     pub fn get(&self, id: &str) -> Option<&Arc<Post>> {
         self.posts.get(id)
+            .or_else(|| self.unlisted.get(id))
+    }
+
+    /// Returns the current ID of the published post which has the given ID as one of its aliases.
+    /// Aliases are only consulted when there is no post with the ID, so a post's current ID always
+    /// takes precedence over another post's alias. If several posts have the alias, the one which
+    /// was published first is returned, so that which one is chosen does not depend on the order
+    /// the posts were inserted in.
+    pub fn resolve_alias(&self, id: &str) -> Option<&Id> {
+        self.aliases_ix
+            .get(id)
+            .and_then(|alias_ix| alias_ix.iter().next())
+            .map(|entry| &entry.id)
+    }
+
+    /// Returns each of the aliases of the published post with the given ID which other published
+    /// posts also have, along with the IDs of those posts.
+    pub fn alias_conflicts(&self, id: &str) -> Vec<(&ShString22, &Id)> {
+        let post = match self.get(id) {
+            Some(post) => post,
+            None => return Vec::new(),
+        };
+
+        post.aliases()
+            .iter()
+            .filter_map(|alias| self.aliases_ix.get_key_value(alias))
+            .flat_map(|(alias, alias_ix)| alias_ix
+                .iter()
+                .filter(|entry| &entry.id != post.id())
+                .map(move |entry| (alias, &entry.id)))
+            .collect()
+    }
+
+    /// Returns the draft post with the given ID. Drafts are kept separately from the published
+    /// posts, so they are never returned by `get` or any of the iterators.
+    pub fn get_draft(&self, id: &str) -> Option<&Arc<Post>> {
+        self.drafts.get(id)
+    }
+
+    /// Returns the post with the given ID if it is scheduled to be published in the future.
+    pub fn get_scheduled(&self, id: &str) -> Option<&Arc<Post>> {
+        self.scheduled.get(id)
     }
 
-    pub fn insert(&mut self, post: Post) -> Option<Arc<Post>> {
+    /// Returns the post with the given ID if it is either a draft or scheduled to be published in
+    /// the future.
+    pub fn get_unpublished(&self, id: &str) -> Option<&Arc<Post>> {
+        self.get_draft(id)
+            .or_else(|| self.get_scheduled(id))
+    }
+
+    /// Returns the post with the given ID, whether it is published, unlisted, a draft or scheduled
+    /// to be published in the future.
+    pub fn get_any(&self, id: &str) -> Option<&Arc<Post>> {
+        self.get(id)
+            .or_else(|| self.get_unpublished(id))
+    }
+
+    /// Adds the post to the store, replacing any post with the same ID. Posts whose publish date
+    /// is after `now` are scheduled rather than published.
+    pub fn insert(&mut self, post: Post, now: DateTime<Utc>) -> Option<Arc<Post>> {
         let old_post = self.remove(post.id());
 
-        // Insert the post into each of the tag indexes.
-        for tag in post.tags() {
-            // First, get the existing `HashSet` for the tag, or create a new one if one does not
-            // already exist. Then, insert the post's ID into the `HashSet`.
-            match self.tags_ix.entry(tag.clone()) {
-                hash_map::Entry::Occupied(entry) => entry.into_mut(),
-                hash_map::Entry::Vacant(entry) => entry.insert(HashSet::new()),
-            }.insert(post.id().clone());
+        // Drafts are not added to any of the indexes, so that they do not appear in any listings.
+        if post.draft() {
+            self.drafts.insert(post.id().clone(), Arc::new(post));
+            return old_post;
         }
 
-        // Insert the post into the correct position of the published BTree index.
-        self.published_ix.insert(PublishedIxEntry::new(&post));
+        // Posts with a publish date in the future are held back until `publish_scheduled` is
+        // called at or after their publish date.
+        if post.published() > now {
+            self.scheduled_ix.insert(PublishedIxEntry::new(&post));
+            self.scheduled.insert(post.id().clone(), Arc::new(post));
+            return old_post;
+        }
 
-        // Wrap the post with an atomic reference counter and insert it into the main posts
-        // `HashMap`.
-        self.posts.insert(post.id().clone(), Arc::new(post));
+        self.insert_visible(Arc::new(post));
 
         old_post
     }
 
     pub fn remove(&mut self, id: &str) -> Option<Arc<Post>> {
-        match self.posts.remove(id) {
-            Some(post) => {
-                // Remove the post's entry in the published index.
-                self.published_ix
-                    .remove(&PublishedIxEntry::new(&post));
-
-                // Remove every occurence of the post from the tags index.
-                for tag in post.tags() {
-                    if let Some(tag_ix) = self.tags_ix.get_mut(tag) {
-                        tag_ix.remove(id);
-                    }
-                }
+        if let Some(post) = self.remove_published(id) {
+            return Some(post);
+        }
+
+        if let Some(post) = self.unlisted.remove(id) {
+            self.remove_aliases(&post);
+            return Some(post);
+        }
 
-                Some(post)
-            },
-            None => None,
+        if let Some(post) = self.scheduled.remove(id) {
+            self.scheduled_ix
+                .remove(&PublishedIxEntry::new(&post));
+            return Some(post);
         }
+
+        self.drafts.remove(id)
     }
 
     pub fn clear(&mut self) {
+        self.scheduled_ix.clear();
+        self.scheduled.clear();
+        self.drafts.clear();
+        self.unlisted.clear();
+        self.aliases_ix.clear();
+        self.series_ix.clear();
+        self.authors_ix.clear();
         self.tags_ix.clear();
+        self.pinned_ix.clear();
         self.published_ix.clear();
         self.posts.clear();
     }
 
+    /// Returns the publish date of the scheduled post which will be published soonest, if there
+    /// are any scheduled posts.
+    pub fn next_scheduled(&self) -> Option<DateTime<Utc>> {
+        self.scheduled_ix
+            .iter()
+            .next()
+            .map(|entry| entry.published)
+    }
+
+    /// Publishes every scheduled post whose publish date is not after `now`, so that they appear
+    /// in the listings. Returns the IDs of the posts that were published.
+    pub fn publish_scheduled(&mut self, now: DateTime<Utc>) -> Vec<Id> {
+        let mut published = Vec::new();
+
+        // The scheduled index is ordered by publish date, so we can stop as soon as we encounter
+        // a post which is still in the future.
+        loop {
+            let entry = match self.scheduled_ix.iter().next() {
+                Some(entry) if entry.published <= now => PublishedIxEntry {
+                    published: entry.published,
+                    id: entry.id.clone(),
+                },
+                _ => break,
+            };
+
+            self.scheduled_ix.remove(&entry);
+
+            if let Some(post) = self.scheduled.remove(&entry.id) {
+                self.insert_visible(post);
+                published.push(entry.id);
+            }
+        }
+
+        published
+    }
+
     pub fn last_updated(&self) -> Option<DateTime<Utc>> {
         self.iter().map(|post| post.updated()).max()
     }
@@ -122,6 +259,20 @@ impl PostsStore {
         self.posts.values()
     }
 
+    /// Returns an iterator over every post in the store, including unlisted posts, drafts and
+    /// scheduled posts, in no particular order.
+    pub fn iter_all(&self)
+    -> impl '_
+        + Iterator<Item = &Arc<Post>>
+        + FusedIterator
+        + Clone
+    {
+        self.posts.values()
+            .chain(self.unlisted.values())
+            .chain(self.drafts.values())
+            .chain(self.scheduled.values())
+    }
+
     pub fn iter_by_published(&self)
     -> impl '_
         + Iterator<Item = &Arc<Post>>
@@ -138,6 +289,227 @@ impl PostsStore {
             .map(|entry| self.get(&entry.id)
                 .expect("invalid entry in `published_ix` pointing to a post that does not exist"))
     }
+
+    /// Returns an iterator over the published posts which are pinned, ordered by publish date.
+    pub fn iter_pinned(&self)
+    -> impl '_
+        + Iterator<Item = &Arc<Post>>
+        + DoubleEndedIterator
+        + ExactSizeIterator
+        + FusedIterator
+        + Clone
+    {
+        // Every entry of the pinned index should contain the ID of a post in the posts map, so
+        // the `expect` should never fail.
+        self.pinned_ix
+            .iter()
+            .map(|entry| self.get(&entry.id)
+                .expect("invalid entry in `pinned_ix` pointing to a post that does not exist"))
+    }
+
+    /// Returns an iterator over the posts in the series with the given name, in the order they
+    /// appear in the series. Returns `None` if there are no published posts in the series.
+    pub fn iter_series(&self, name: &str)
+    -> Option<impl '_
+        + Iterator<Item = &Arc<Post>>
+        + DoubleEndedIterator
+        + ExactSizeIterator
+        + FusedIterator
+        + Clone>
+    {
+        // Every entry of the series index should contain the ID of a post in the posts map, so
+        // the `expect` should never fail.
+        self.series_ix
+            .get(name)
+            .map(|series_ix| series_ix
+                .iter()
+                .map(|entry| self.get(&entry.id)
+                    .expect("invalid entry in `series_ix` pointing to a post that does not exist")))
+    }
+
+    /// Normalises the given tag and returns its canonical name, or `None` if the tag is invalid.
+    pub fn resolve_tag(&self, tag: &str) -> Option<ShString22> {
+        self.tags.resolve(tag).ok()
+    }
+
+    /// Returns the canonical names of the post's tags, without duplicates. Tags which are invalid
+    /// are left out.
+    pub fn post_tags(&self, post: &Post) -> Vec<ShString22> {
+        let mut tags = Vec::<ShString22>::with_capacity(post.tags().len());
+        for tag in post.tags() {
+            if let Ok(tag) = self.tags.resolve(tag) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+        tags
+    }
+
+    /// Returns an iterator over the published posts with the given tag, ordered by publish date.
+    /// The tag is normalised and resolved in the same way as post tags. Returns `None` if there
+    /// are no published posts with the tag.
+    pub fn iter_by_tag(&self, tag: &str)
+    -> Option<impl '_
+        + Iterator<Item = &Arc<Post>>
+        + DoubleEndedIterator
+        + ExactSizeIterator
+        + FusedIterator
+        + Clone>
+    {
+        let tag = self.resolve_tag(tag)?;
+
+        // Every entry of the tags index should contain the ID of a post in the posts map, so the
+        // `expect` should never fail.
+        self.tags_ix
+            .get(&tag)
+            .map(|tag_ix| tag_ix
+                .iter()
+                .map(|entry| self.get(&entry.id)
+                    .expect("invalid entry in `tags_ix` pointing to a post that does not exist")))
+    }
+
+    /// Returns an iterator over the published posts by the given author, ordered by publish date.
+    /// Returns `None` if there are no published posts by the author.
+    pub fn iter_by_author(&self, author: &str)
+    -> Option<impl '_
+        + Iterator<Item = &Arc<Post>>
+        + DoubleEndedIterator
+        + ExactSizeIterator
+        + FusedIterator
+        + Clone>
+    {
+        // Every entry of the authors index should contain the ID of a post in the posts map, so
+        // the `expect` should never fail.
+        self.authors_ix
+            .get(author)
+            .map(|author_ix| author_ix
+                .iter()
+                .map(|entry| self.get(&entry.id)
+                    .expect("invalid entry in `authors_ix` pointing to a post that does not exist")))
+    }
+
+    /// Inserts a post which is ready to be viewed. Unlisted posts are kept separately from the
+    /// other published posts and are not added to any of the indexes apart from the aliases
+    /// index, so that they can be found with `get` and through their old IDs but do not appear in
+    /// any listings, feeds or sitemaps.
+    fn insert_visible(&mut self, post: Arc<Post>) {
+        // Point each of the post's aliases to the post's current ID. Other posts may claim the
+        // same alias, in which case the alias resolves to the one which was published first.
+        for alias in post.aliases() {
+            match self.aliases_ix.entry(alias.clone()) {
+                hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                hash_map::Entry::Vacant(entry) => entry.insert(BTreeSet::new()),
+            }.insert(PublishedIxEntry::new(&post));
+        }
+
+        if post.unlisted() {
+            self.unlisted.insert(post.id().clone(), post);
+        } else {
+            self.insert_published(post);
+        }
+    }
+
+    fn insert_published(&mut self, post: Arc<Post>) {
+        // Insert the post into each of the tag indexes, using the canonical name of each tag.
+        for tag in self.post_tags(&post) {
+            // First, get the existing `BTreeSet` for the tag, or create a new one if one does not
+            // already exist. Then, insert the post into the `BTreeSet`.
+            match self.tags_ix.entry(tag) {
+                hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                hash_map::Entry::Vacant(entry) => entry.insert(BTreeSet::new()),
+            }.insert(PublishedIxEntry::new(&post));
+        }
+
+        // Insert the post into the index of each of its authors.
+        for author in post.authors() {
+            match self.authors_ix.entry(author.clone()) {
+                hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                hash_map::Entry::Vacant(entry) => entry.insert(BTreeSet::new()),
+            }.insert(PublishedIxEntry::new(&post));
+        }
+
+        // Insert the post into the correct position of its series' index, if it is part of a
+        // series.
+        if let Some(series) = post.series() {
+            match self.series_ix.entry(series.name().clone()) {
+                hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                hash_map::Entry::Vacant(entry) => entry.insert(BTreeSet::new()),
+            }.insert(SeriesIxEntry::new(&post));
+        }
+
+        // Insert the post into the pinned index if it is pinned.
+        if post.pinned() {
+            self.pinned_ix.insert(PublishedIxEntry::new(&post));
+        }
+
+        // Insert the post into the correct position of the published BTree index.
+        self.published_ix.insert(PublishedIxEntry::new(&post));
+
+        // Insert the post into the main posts `HashMap`.
+        self.posts.insert(post.id().clone(), post);
+    }
+
+    fn remove_published(&mut self, id: &str) -> Option<Arc<Post>> {
+        let post = self.posts.remove(id)?;
+
+        // Remove the post's entry in the published index.
+        self.published_ix
+            .remove(&PublishedIxEntry::new(&post));
+
+        self.pinned_ix
+            .remove(&PublishedIxEntry::new(&post));
+
+        // Remove every occurence of the post from the tags index, removing tags which no longer
+        // have any posts.
+        for tag in self.post_tags(&post) {
+            if let hash_map::Entry::Occupied(mut entry) = self.tags_ix.entry(tag) {
+                entry.get_mut().remove(&PublishedIxEntry::new(&post));
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+
+        // Remove the post from the index of each of its authors, removing authors who no longer
+        // have any posts.
+        for author in post.authors() {
+            if let hash_map::Entry::Occupied(mut entry) = self.authors_ix.entry(author.clone()) {
+                entry.get_mut().remove(&PublishedIxEntry::new(&post));
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+
+        self.remove_aliases(&post);
+
+        // Remove the post from its series' index, and remove the series altogether if this was
+        // the last post in it so that it is no longer reported as existing.
+        if let Some(series) = post.series() {
+            if let hash_map::Entry::Occupied(mut entry) = self.series_ix.entry(series.name().clone()) {
+                entry.get_mut().remove(&SeriesIxEntry::new(&post));
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+
+        Some(post)
+    }
+
+    /// Removes the post from the index of each of its aliases, removing aliases which no longer
+    /// belong to any posts.
+    fn remove_aliases(&mut self, post: &Post) {
+        for alias in post.aliases() {
+            if let hash_map::Entry::Occupied(mut entry) = self.aliases_ix.entry(alias.clone()) {
+                entry.get_mut().remove(&PublishedIxEntry::new(post));
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+    }
 }
 
 impl Default for PostsStore {
@@ -160,3 +532,222 @@ impl PublishedIxEntry {
         }
     }
 }
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesIxEntry {
+    part: u32,
+    published: DateTime<Utc>,
+    id: Id,
+}
+
+impl SeriesIxEntry {
+    fn new(post: &Post) -> Self {
+        Self {
+            // Posts without a part number are ordered after all of the numbered parts.
+            part: post.series()
+                .and_then(|series| series.part())
+                .unwrap_or(u32::MAX),
+            published: post.published(),
+            id: post.id().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, Duration, Utc};
+    use libshire::uuid::Uuid;
+
+    use crate::{
+        codeblock::CodeBlockRenderer,
+        post::{Id, LinkTargets, Post},
+        shortcode::ShortcodeRegistry,
+        test_util::toml_header,
+    };
+
+    use super::PostsStore;
+
+    fn post(id: &str, extra: &str) -> Post {
+        Post::new_from_str(
+            &CodeBlockRenderer::new(),
+            &ShortcodeRegistry::new(),
+            &LinkTargets::new(),
+            Uuid::nil(),
+            Id::new(id).unwrap(),
+            None,
+            &format!("{}\n---\nContent.\n", toml_header(extra))
+        ).unwrap()
+    }
+
+    fn time(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn ids<'a, I>(posts: I) -> Vec<&'a str>
+    where
+        I: Iterator<Item = &'a Arc<Post>>,
+    {
+        posts.map(|post| &**post.id()).collect()
+    }
+
+    #[test]
+    fn drafts_are_only_found_with_get_draft() {
+        let now = time("2022-06-01T12:00:00Z");
+        let mut store = PostsStore::new();
+        store.insert(post("draft", "draft = true\ntags = [\"rust\"]\naliases = [\"old-draft\"]\n\n[series]\nname = \"s\""), now);
+
+        assert!(store.get("draft").is_none());
+        assert!(store.get_scheduled("draft").is_none());
+        assert!(store.resolve_alias("old-draft").is_none());
+        assert_eq!(store.iter().count(), 0);
+        assert_eq!(store.iter_by_published().count(), 0);
+        assert!(store.iter_by_tag("rust").is_none());
+        assert!(store.iter_series("s").is_none());
+        assert!(store.iter_by_author("me").is_none());
+        assert!(store.last_updated().is_none());
+
+        assert_eq!(store.get_draft("draft").map(|post| &**post.id()), Some("draft"));
+        assert_eq!(store.get_unpublished("draft").map(|post| &**post.id()), Some("draft"));
+        assert_eq!(store.get_any("draft").map(|post| &**post.id()), Some("draft"));
+        assert_eq!(ids(store.iter_all()), ["draft"]);
+    }
+
+    #[test]
+    fn publishing_a_draft() {
+        let now = time("2022-06-01T12:00:00Z");
+        let mut store = PostsStore::new();
+        store.insert(post("post", "draft = true\ntags = [\"rust\"]"), now);
+        store.insert(post("post", "tags = [\"rust\"]"), now);
+
+        assert!(store.get_draft("post").is_none());
+        assert!(store.get_unpublished("post").is_none());
+        assert!(store.get("post").is_some());
+        assert!(store.get_any("post").is_some());
+        assert_eq!(ids(store.iter_by_tag("rust").unwrap()), ["post"]);
+
+        // Turning the post back into a draft hides it again.
+        store.insert(post("post", "draft = true\ntags = [\"rust\"]"), now);
+        assert!(store.get("post").is_none());
+        assert!(store.iter_by_tag("rust").is_none());
+        assert!(store.get_draft("post").is_some());
+    }
+
+    #[test]
+    fn scheduled_posts_are_published_at_their_publish_date() {
+        let now = time("2022-06-01T12:00:00Z");
+        let due = time("2022-06-02T12:00:00Z");
+        let mut store = PostsStore::new();
+        store.insert(post("old", "published = \"2022-05-01T12:00:00Z\"\ntags = [\"rust\"]"), now);
+        store.insert(post("new", "published = \"2022-06-02T12:00:00Z\"\ntags = [\"rust\"]\n\n[series]\nname = \"s\""), now);
+
+        assert!(store.get("new").is_none());
+        assert!(store.get_scheduled("new").is_some());
+        assert_eq!(ids(store.iter_by_published()), ["old"]);
+        assert_eq!(ids(store.iter_by_tag("rust").unwrap()), ["old"]);
+        assert!(store.iter_series("s").is_none());
+        assert_eq!(store.next_scheduled(), Some(due));
+
+        assert!(store.publish_scheduled(due - Duration::seconds(1)).is_empty());
+        assert!(store.get("new").is_none());
+
+        assert_eq!(store.publish_scheduled(due), [Id::new("new").unwrap()]);
+        assert!(store.get("new").is_some());
+        assert!(store.get_scheduled("new").is_none());
+        assert_eq!(ids(store.iter_by_published()), ["old", "new"]);
+        assert_eq!(ids(store.iter_by_tag("rust").unwrap()), ["old", "new"]);
+        assert_eq!(ids(store.iter_series("s").unwrap()), ["new"]);
+        assert_eq!(store.next_scheduled(), None);
+        assert!(store.publish_scheduled(due).is_empty());
+    }
+
+    #[test]
+    fn editing_the_publish_date() {
+        let now = time("2022-06-01T12:00:00Z");
+        let mut store = PostsStore::new();
+
+        // Moving a scheduled post's publish date later reschedules it.
+        store.insert(post("post", "published = \"2022-06-02T12:00:00Z\""), now);
+        store.insert(post("post", "published = \"2022-06-03T12:00:00Z\""), now);
+        assert_eq!(store.next_scheduled(), Some(time("2022-06-03T12:00:00Z")));
+        assert!(store.publish_scheduled(time("2022-06-02T12:00:00Z")).is_empty());
+
+        // Moving it earlier than the current time publishes it straight away.
+        store.insert(post("post", "published = \"2022-05-01T12:00:00Z\""), now);
+        assert_eq!(store.next_scheduled(), None);
+        assert_eq!(ids(store.iter_by_published()), ["post"]);
+
+        // Moving a published post's publish date into the future hides it again until then.
+        store.insert(post("post", "published = \"2022-06-04T12:00:00Z\""), now);
+        assert!(store.get("post").is_none());
+        assert_eq!(store.iter_by_published().count(), 0);
+        assert_eq!(store.next_scheduled(), Some(time("2022-06-04T12:00:00Z")));
+        assert_eq!(store.publish_scheduled(time("2022-06-05T12:00:00Z")), [Id::new("post").unwrap()]);
+        assert_eq!(ids(store.iter_by_published()), ["post"]);
+    }
+
+    #[test]
+    fn series_order() {
+        let now = time("2022-06-01T12:00:00Z");
+        let part = |published: &str, part: Option<u32>| {
+            let part = part.map(|part| format!("part = {}\n", part)).unwrap_or_default();
+            format!("published = \"{}\"\n\n[series]\nname = \"s\"\n{}", published, part)
+        };
+
+        let mut store = PostsStore::new();
+        store.insert(post("unnumbered-later", &part("2022-05-04T12:00:00Z", None)), now);
+        store.insert(post("second-later", &part("2022-05-03T12:00:00Z", Some(2))), now);
+        store.insert(post("unnumbered-earlier", &part("2022-05-01T12:00:00Z", None)), now);
+        store.insert(post("first", &part("2022-05-05T12:00:00Z", Some(1))), now);
+        store.insert(post("second-earlier", &part("2022-05-02T12:00:00Z", Some(2))), now);
+
+        // Numbered parts come first in order of their numbers, then the parts without numbers.
+        // Parts with the same number are ordered by publish date.
+        assert_eq!(
+            ids(store.iter_series("s").unwrap()),
+            ["first", "second-earlier", "second-later", "unnumbered-earlier", "unnumbered-later"]
+        );
+
+        store.remove("first");
+        store.remove("second-earlier");
+        store.remove("second-later");
+        assert_eq!(ids(store.iter_series("s").unwrap()), ["unnumbered-earlier", "unnumbered-later"]);
+
+        store.remove("unnumbered-earlier");
+        store.remove("unnumbered-later");
+        assert!(store.iter_series("s").is_none());
+    }
+
+    #[test]
+    fn alias_claimed_by_several_posts() {
+        let now = time("2022-06-01T12:00:00Z");
+        let first = || post("first", "published = \"2022-05-01T12:00:00Z\"\naliases = [\"old\"]");
+        let second = || post("second", "published = \"2022-05-02T12:00:00Z\"\naliases = [\"old\", \"other\"]");
+
+        // The alias resolves to the post which was published first, whichever order the posts
+        // are inserted in.
+        let mut store = PostsStore::new();
+        store.insert(first(), now);
+        store.insert(second(), now);
+        assert_eq!(store.resolve_alias("old").map(|id| &**id), Some("first"));
+
+        let mut store = PostsStore::new();
+        store.insert(second(), now);
+        store.insert(first(), now);
+        assert_eq!(store.resolve_alias("old").map(|id| &**id), Some("first"));
+        assert_eq!(store.resolve_alias("other").map(|id| &**id), Some("second"));
+
+        let conflicts = store.alias_conflicts("second");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((&**conflicts[0].0, &**conflicts[0].1), ("old", "first"));
+        assert!(store.alias_conflicts("missing").is_empty());
+
+        store.remove("first");
+        assert_eq!(store.resolve_alias("old").map(|id| &**id), Some("second"));
+        assert!(store.alias_conflicts("second").is_empty());
+
+        store.remove("second");
+        assert!(store.resolve_alias("old").is_none());
+    }
+}
@@ -1,4 +1,10 @@
 pub mod codeblock;
 pub mod db;
+pub mod math;
 pub mod post;
+pub mod shortcode;
+pub mod tag;
 pub mod time;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,1150 @@
+use std::{error, fmt, ops::Range};
+
+use maud::{Markup, PreEscaped};
+use pulldown_cmark::escape::escape_html;
+
+/// Whether math is part of a line of text or shown as a block of its own.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MathMode {
+    Inline,
+    Display,
+}
+
+/// Converts TeX math to MathML, so that it can be displayed without any client-side JavaScript.
+/// The TeX is kept as an annotation of the MathML, so that it is copied along with the rendered
+/// math.
+///
+/// Only a small subset of TeX is supported, which covers the math used in most posts:
+///
+/// - letters, numbers, operators and brackets, including `\{`, `\langle` and `\lfloor`;
+/// - Greek letters and common symbols, relations, arrows and binary operators, such as `\infty`,
+///   `\leq`, `\to` and `\times`;
+/// - the large operators `\sum`, `\prod`, `\bigcup`, `\bigcap`, `\int`, `\iint` and `\oint`,
+///   and functions such as `\sin` and `\lim`;
+/// - subscripts, superscripts and primes;
+/// - `\frac`, `\sqrt`, `\text`, `\mathrm`, `\mathbf` and `\mathbb`;
+/// - the accents `\hat`, `\bar`, `\vec`, `\tilde`, `\dot` and `\overline`;
+/// - the spacing commands `\,`, `\:`, `\;`, `\!`, `\ `, `\quad` and `\qquad`.
+///
+/// Anything else, such as `\left` and `\right` or environments like `aligned`, is reported as an
+/// error.
+pub fn render(tex: &str, mode: MathMode) -> Result<Markup, MathError> {
+    let nodes = TexParser::new(tex).parse()?;
+
+    let mut buf = String::new();
+
+    buf.push_str(match mode {
+        MathMode::Inline => "<math>",
+        MathMode::Display => "<math display=\"block\">",
+    });
+    buf.push_str("<semantics><mrow>");
+    write_nodes(&mut buf, &nodes);
+    buf.push_str("</mrow><annotation encoding=\"application/x-tex\">");
+    push_escaped(&mut buf, tex.trim());
+    buf.push_str("</annotation></semantics></math>");
+
+    Ok(PreEscaped(buf))
+}
+
+/// An error in a piece of TeX math, which could not be converted to MathML.
+#[derive(Clone, Debug)]
+pub struct MathError {
+    message: String,
+    offset: usize,
+    len: usize,
+}
+
+impl MathError {
+    #[inline]
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the byte range of the part of the TeX source which caused the error.
+    #[inline]
+    #[must_use]
+    pub fn span(&self) -> Range<usize> {
+        self.offset..(self.offset + self.len)
+    }
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for MathError {}
+
+enum Node {
+    Ident {
+        text: String,
+        upright: bool,
+    },
+    Number(String),
+    Function(String),
+    Op(Op),
+    Text(String),
+    Space(&'static str),
+    Row(Vec<Node>),
+    Frac {
+        num: Box<Node>,
+        den: Box<Node>,
+    },
+    Sqrt(Box<Node>),
+    Root {
+        base: Box<Node>,
+        index: Box<Node>,
+    },
+    Scripts {
+        base: Box<Node>,
+        sub: Option<Box<Node>>,
+        sup: Option<Box<Node>>,
+    },
+    Limits {
+        base: Box<Node>,
+        under: Option<Box<Node>>,
+        over: Option<Box<Node>>,
+    },
+    Accent {
+        base: Box<Node>,
+        accent: Op,
+    },
+}
+
+impl Node {
+    /// Returns whether scripts attached to the node should be placed above and below it rather
+    /// than to its right, as they are for `\sum` and `\lim`.
+    fn takes_limits(&self) -> bool {
+        matches!(self, Self::Op(op) if op.limits)
+    }
+
+    fn write(&self, buf: &mut String) {
+        match self {
+            Self::Ident { text, upright } => {
+                buf.push_str(if *upright { "<mi mathvariant=\"normal\">" } else { "<mi>" });
+                push_escaped(buf, text);
+                buf.push_str("</mi>");
+            },
+
+            Self::Number(number) => {
+                buf.push_str("<mn>");
+                push_escaped(buf, number);
+                buf.push_str("</mn>");
+            },
+
+            // Identifiers with more than one character are upright by default.
+            Self::Function(name) => {
+                buf.push_str("<mi>");
+                push_escaped(buf, name);
+                buf.push_str("</mi>");
+            },
+
+            Self::Op(op) => op.write(buf),
+
+            Self::Text(text) => {
+                buf.push_str("<mtext>");
+                push_escaped(buf, text);
+                buf.push_str("</mtext>");
+            },
+
+            Self::Space(width) => {
+                buf.push_str(&format!("<mspace width=\"{}\"></mspace>", width));
+            },
+
+            Self::Row(nodes) => {
+                buf.push_str("<mrow>");
+                write_nodes(buf, nodes);
+                buf.push_str("</mrow>");
+            },
+
+            Self::Frac { num, den } => {
+                buf.push_str("<mfrac>");
+                num.write(buf);
+                den.write(buf);
+                buf.push_str("</mfrac>");
+            },
+
+            Self::Sqrt(base) => {
+                buf.push_str("<msqrt>");
+                base.write(buf);
+                buf.push_str("</msqrt>");
+            },
+
+            Self::Root { base, index } => {
+                buf.push_str("<mroot>");
+                base.write(buf);
+                index.write(buf);
+                buf.push_str("</mroot>");
+            },
+
+            Self::Scripts { base, sub, sup } => {
+                let element = match (sub, sup) {
+                    (Some(_), Some(_)) => "msubsup",
+                    (Some(_), None) => "msub",
+                    (None, _) => "msup",
+                };
+                buf.push_str(&format!("<{}>", element));
+                base.write(buf);
+                for script in sub.iter().chain(sup) {
+                    script.write(buf);
+                }
+                buf.push_str(&format!("</{}>", element));
+            },
+
+            Self::Limits { base, under, over } => {
+                let element = match (under, over) {
+                    (Some(_), Some(_)) => "munderover",
+                    (Some(_), None) => "munder",
+                    (None, _) => "mover",
+                };
+                buf.push_str(&format!("<{}>", element));
+                base.write(buf);
+                for limit in under.iter().chain(over) {
+                    limit.write(buf);
+                }
+                buf.push_str(&format!("</{}>", element));
+            },
+
+            Self::Accent { base, accent } => {
+                buf.push_str("<mover accent=\"true\">");
+                base.write(buf);
+                accent.write(buf);
+                buf.push_str("</mover>");
+            },
+        }
+    }
+}
+
+fn write_nodes(buf: &mut String, nodes: &[Node]) {
+    for node in nodes {
+        node.write(buf);
+    }
+}
+
+fn push_escaped(buf: &mut String, s: &str) {
+    // Writing to a `String` cannot fail, so the result is ignored.
+    let _ = escape_html(buf, s);
+}
+
+struct Op {
+    text: String,
+    stretchy: Option<bool>,
+    /// Whether scripts attached to the operator are placed above and below it in display math.
+    limits: bool,
+    /// Whether the operator is written as a word, such as `lim`.
+    word: bool,
+}
+
+impl Op {
+    fn new(text: &str) -> Self {
+        Self {
+            text: text.to_owned(),
+            stretchy: None,
+            limits: false,
+            word: false,
+        }
+    }
+
+    /// An operator which does not grow to the height of its surroundings, unlike brackets in
+    /// MathML by default. Brackets only grow in TeX when `\left` and `\right` are used.
+    fn fixed(text: &str) -> Self {
+        Self {
+            stretchy: Some(false),
+            ..Self::new(text)
+        }
+    }
+
+    /// An operator written as a word, such as `lim`, whose scripts are placed above and below it
+    /// in display math.
+    fn limit_word(text: &str) -> Self {
+        Self {
+            limits: true,
+            word: true,
+            ..Self::new(text)
+        }
+    }
+
+    fn write(&self, buf: &mut String) {
+        buf.push_str("<mo");
+        if self.word {
+            buf.push_str(" form=\"prefix\" movablelimits=\"true\"");
+        }
+        if let Some(stretchy) = self.stretchy {
+            buf.push_str(&format!(" stretchy=\"{}\"", stretchy));
+        }
+        buf.push('>');
+        push_escaped(buf, &self.text);
+        buf.push_str("</mo>");
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Variant {
+    Upright,
+    Bold,
+    DoubleStruck,
+}
+
+impl Variant {
+    /// Returns the character from the Unicode Mathematical Alphanumeric Symbols block for the
+    /// given letter or digit in this style.
+    fn apply_char(self, c: char) -> char {
+        let (upper, lower, digit) = match self {
+            Self::Upright => return c,
+            Self::Bold => (0x1D400, 0x1D41A, 0x1D7CE),
+            Self::DoubleStruck => (0x1D538, 0x1D552, 0x1D7D8),
+        };
+
+        // Some double-struck letters were encoded in Unicode before the rest of their style, so
+        // the block has gaps where they would be.
+        let exception = match (self, c) {
+            (Self::DoubleStruck, 'C') => Some('\u{2102}'),
+            (Self::DoubleStruck, 'H') => Some('\u{210D}'),
+            (Self::DoubleStruck, 'N') => Some('\u{2115}'),
+            (Self::DoubleStruck, 'P') => Some('\u{2119}'),
+            (Self::DoubleStruck, 'Q') => Some('\u{211A}'),
+            (Self::DoubleStruck, 'R') => Some('\u{211D}'),
+            (Self::DoubleStruck, 'Z') => Some('\u{2124}'),
+            _ => None,
+        };
+
+        if let Some(exception) = exception {
+            return exception;
+        }
+
+        let code_point = match c {
+            'A'..='Z' => upper + (c as u32 - 'A' as u32),
+            'a'..='z' => lower + (c as u32 - 'a' as u32),
+            '0'..='9' => digit + (c as u32 - '0' as u32),
+            _ => return c,
+        };
+
+        char::from_u32(code_point).unwrap_or(c)
+    }
+
+    fn apply(self, node: &mut Node) {
+        match node {
+            Node::Ident { text, upright } => {
+                *text = text.chars().map(|c| self.apply_char(c)).collect();
+                // None of the styles are italic, so stop the browser from making single letters
+                // italic.
+                *upright = true;
+            },
+            Node::Number(number) | Node::Text(number) => {
+                *number = number.chars().map(|c| self.apply_char(c)).collect();
+            },
+            Node::Row(nodes) => {
+                for node in nodes {
+                    self.apply(node);
+                }
+            },
+            Node::Frac { num, den } => {
+                self.apply(num);
+                self.apply(den);
+            },
+            Node::Sqrt(base)
+            | Node::Root { base, .. }
+            | Node::Scripts { base, .. }
+            | Node::Limits { base, .. }
+            | Node::Accent { base, .. } => self.apply(base),
+            Node::Function(_) | Node::Op(_) | Node::Space(_) => (),
+        }
+    }
+}
+
+/// The reason a list of nodes ended.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stop {
+    Eof,
+    CloseBrace,
+    CloseBracket,
+}
+
+/// The maximum number of lists and atoms which can be nested inside each other, so that deeply
+/// nested math such as a long run of `{` is reported as an error rather than overflowing the stack.
+const MAX_DEPTH: usize = 100;
+
+struct TexParser<'a> {
+    tex: &'a str,
+    pos: usize,
+    /// The number of calls to `parse_list` and `parse_atom` which are in progress.
+    depth: usize,
+}
+
+impl<'a> TexParser<'a> {
+    fn new(tex: &'a str) -> Self {
+        Self { tex, pos: 0, depth: 0 }
+    }
+
+    fn parse(mut self) -> Result<Vec<Node>, MathError> {
+        let (nodes, stop, stop_pos) = self.parse_list(false)?;
+
+        let message = match stop {
+            Stop::Eof => return Ok(nodes),
+            Stop::CloseBrace => "unmatched `}`",
+            Stop::CloseBracket => "unmatched `]`",
+        };
+
+        Err(self.error_at(stop_pos, message.to_owned()))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.tex[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    /// Creates an error which points from the given position to the current position.
+    fn error_at(&self, start: usize, message: String) -> MathError {
+        let len = self.tex[start..]
+            .chars()
+            .next()
+            .map_or(0, char::len_utf8)
+            .max(self.pos.saturating_sub(start));
+
+        MathError {
+            message,
+            offset: start,
+            len,
+        }
+    }
+
+    /// Calls the given parsing function one level of nesting deeper, returning an error instead if
+    /// the math is nested too deeply.
+    fn nested<T, F>(&mut self, parse: F) -> Result<T, MathError>
+    where
+        F: FnOnce(&mut Self) -> Result<T, MathError>,
+    {
+        if self.depth >= MAX_DEPTH {
+            self.skip_whitespace();
+            return Err(self.error_at(self.pos, "math is nested too deeply".to_owned()));
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Parses nodes until the end of the input or a token which ends a list, such as `}`.
+    /// Returns the nodes, the reason the list ended and the position of the token which ended it.
+    fn parse_list(&mut self, in_bracket: bool) -> Result<(Vec<Node>, Stop, usize), MathError> {
+        self.nested(|parser| parser.parse_list_nodes(in_bracket))
+    }
+
+    fn parse_list_nodes(&mut self, in_bracket: bool) -> Result<(Vec<Node>, Stop, usize), MathError> {
+        let mut nodes = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            let start = self.pos;
+
+            let stop = match self.peek() {
+                None => Some(Stop::Eof),
+                Some('}') => Some(Stop::CloseBrace),
+                Some(']') if in_bracket => Some(Stop::CloseBracket),
+                _ => None,
+            };
+
+            if let Some(stop) = stop {
+                self.bump();
+                return Ok((nodes, stop, start));
+            }
+
+            let atom = match self.peek() {
+                // A script with nothing before it is attached to an empty base, as in `{}^{14}C`.
+                Some('^' | '_' | '\'') => Node::Row(Vec::new()),
+                _ => self.parse_atom(false)?,
+            };
+
+            let is_function = matches!(atom, Node::Function(_));
+
+            nodes.push(self.parse_scripts(atom)?);
+
+            // Mark the application of a function such as `\sin` to its argument.
+            if is_function {
+                nodes.push(Node::Op(Op::new("\u{2061}")));
+            }
+        }
+    }
+
+    /// Parses a single unit of math: a character, a command with its arguments, or a group in
+    /// braces. If `single_digit` is set, a number is only one digit long, as it is for the
+    /// arguments of commands such as `\frac12`.
+    fn parse_atom(&mut self, single_digit: bool) -> Result<Node, MathError> {
+        self.nested(|parser| parser.parse_atom_node(single_digit))
+    }
+
+    fn parse_atom_node(&mut self, single_digit: bool) -> Result<Node, MathError> {
+        self.skip_whitespace();
+
+        let start = self.pos;
+
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Err(self.error_at(start, "expected an argument, found the end of the math".to_owned())),
+        };
+
+        let node = match c {
+            '{' => {
+                let (nodes, stop, _) = self.parse_list(false)?;
+                if stop != Stop::CloseBrace {
+                    return Err(self.error_at(start, "unmatched `{`".to_owned()));
+                }
+                Node::Row(nodes)
+            },
+
+            '\\' => self.parse_command(start)?,
+
+            '0'..='9' => {
+                if !single_digit {
+                    loop {
+                        let rest = &self.tex[self.pos..];
+                        let mut chars = rest.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some('0'..='9'), _) => self.pos += 1,
+                            (Some('.'), Some('0'..='9')) => self.pos += 2,
+                            _ => break,
+                        }
+                    }
+                }
+                Node::Number(self.tex[start..self.pos].to_owned())
+            },
+
+            c if c.is_alphabetic() => Node::Ident {
+                text: c.to_string(),
+                upright: false,
+            },
+
+            '.' => Node::Ident {
+                text: ".".to_owned(),
+                upright: true,
+            },
+
+            '-' => Node::Op(Op::new("\u{2212}")),
+            '*' => Node::Op(Op::new("\u{2217}")),
+            '\'' => Node::Op(Op::new("\u{2032}")),
+            '~' => Node::Space("0.3333em"),
+            '(' | ')' | '[' | ']' | '|' | '/' => Node::Op(Op::fixed(&c.to_string())),
+
+            '}' | '^' | '_' | '&' | '#' | '$' => {
+                return Err(self.error_at(start, format!("unexpected `{}`", c)));
+            },
+
+            '%' => return Err(self.error_at(start, "unexpected `%`; write `\\%` for a percent sign".to_owned())),
+
+            c if c.is_numeric() => Node::Number(c.to_string()),
+
+            c => Node::Op(Op::new(&c.to_string())),
+        };
+
+        Ok(node)
+    }
+
+    /// Parses an argument of a command.
+    fn parse_arg(&mut self) -> Result<Node, MathError> {
+        self.parse_atom(true)
+    }
+
+    /// Reads the name of a command, just after its backslash. Command names are either a run of
+    /// letters or a single other character.
+    fn command_name(&mut self) -> Result<&'a str, MathError> {
+        let start = self.pos;
+
+        match self.bump() {
+            Some(c) if c.is_ascii_alphabetic() => {
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphabetic()) {
+                    self.bump();
+                }
+            },
+            Some(_) => (),
+            None => return Err(self.error_at(start.saturating_sub(1), "expected a command name after `\\`".to_owned())),
+        }
+
+        Ok(&self.tex[start..self.pos])
+    }
+
+    /// Parses a command. `start` is the position of its backslash, which has already been read.
+    fn parse_command(&mut self, start: usize) -> Result<Node, MathError> {
+        let name = self.command_name()?;
+
+        if let Some((_, text, upright)) = IDENTS.iter().find(|(command, ..)| *command == name) {
+            return Ok(Node::Ident {
+                text: (*text).to_owned(),
+                upright: *upright,
+            });
+        }
+
+        if let Some((_, text)) = DELIMITERS.iter().find(|(command, _)| *command == name) {
+            return Ok(Node::Op(Op::fixed(text)));
+        }
+
+        if let Some((_, text)) = OPS.iter().find(|(command, _)| *command == name) {
+            return Ok(Node::Op(Op::new(text)));
+        }
+
+        if let Some((_, text)) = BIG_OPS.iter().find(|(command, _)| *command == name) {
+            return Ok(Node::Op(Op {
+                limits: true,
+                ..Op::new(text)
+            }));
+        }
+
+        if let Some((_, text)) = INTEGRALS.iter().find(|(command, _)| *command == name) {
+            return Ok(Node::Op(Op::new(text)));
+        }
+
+        if FUNCTIONS.contains(&name) {
+            return Ok(Node::Function(name.to_owned()));
+        }
+
+        if LIMIT_FUNCTIONS.contains(&name) {
+            return Ok(Node::Op(Op::limit_word(name)));
+        }
+
+        if let Some((_, width)) = SPACES.iter().find(|(command, _)| *command == name) {
+            return Ok(Node::Space(width));
+        }
+
+        if let Some((_, variant)) = VARIANTS.iter().find(|(command, _)| *command == name) {
+            let mut arg = self.parse_arg()?;
+            variant.apply(&mut arg);
+            return Ok(arg);
+        }
+
+        if let Some((_, accent, stretchy)) = ACCENTS.iter().find(|(command, ..)| *command == name) {
+            let base = self.parse_arg()?;
+            return Ok(Node::Accent {
+                base: Box::new(base),
+                accent: Op {
+                    stretchy: Some(*stretchy),
+                    ..Op::new(accent)
+                },
+            });
+        }
+
+        let node = match name {
+            "frac" => {
+                let num = self.parse_arg()?;
+                let den = self.parse_arg()?;
+                Node::Frac {
+                    num: Box::new(num),
+                    den: Box::new(den),
+                }
+            },
+
+            "sqrt" => {
+                self.skip_whitespace();
+                let index_start = self.pos;
+                if self.eat('[') {
+                    let (index, stop, _) = self.parse_list(true)?;
+                    if stop != Stop::CloseBracket {
+                        return Err(self.error_at(index_start, "unmatched `[`".to_owned()));
+                    }
+                    let base = self.parse_arg()?;
+                    Node::Root {
+                        base: Box::new(base),
+                        index: Box::new(Node::Row(index)),
+                    }
+                } else {
+                    Node::Sqrt(Box::new(self.parse_arg()?))
+                }
+            },
+
+            "text" => Node::Text(self.parse_raw_group(name)?),
+
+            _ => {
+                return Err(self.error_at(start, format!("unsupported command `\\{}`", name)));
+            },
+        };
+
+        Ok(node)
+    }
+
+    /// Parses any subscript, superscript and primes following a node.
+    fn parse_scripts(&mut self, base: Node) -> Result<Node, MathError> {
+        let mut sub = None;
+        let mut sup = None;
+        let mut primes = 0;
+
+        loop {
+            self.skip_whitespace();
+
+            let start = self.pos;
+
+            match self.peek() {
+                Some('^') => {
+                    self.bump();
+                    if sup.is_some() {
+                        return Err(self.error_at(start, "double superscript; use braces to group the superscript".to_owned()));
+                    }
+                    sup = Some(self.parse_arg()?);
+                },
+                Some('_') => {
+                    self.bump();
+                    if sub.is_some() {
+                        return Err(self.error_at(start, "double subscript; use braces to group the subscript".to_owned()));
+                    }
+                    sub = Some(self.parse_arg()?);
+                },
+                Some('\'') => {
+                    self.bump();
+                    if sup.is_some() {
+                        return Err(self.error_at(start, "prime after a superscript; write the prime first".to_owned()));
+                    }
+                    primes += 1;
+                },
+                _ => break,
+            }
+        }
+
+        if primes > 0 {
+            let primes = Node::Op(Op::new(&"\u{2032}".repeat(primes)));
+            sup = Some(match sup {
+                Some(sup) => Node::Row(vec![primes, sup]),
+                None => primes,
+            });
+        }
+
+        if sub.is_none() && sup.is_none() {
+            return Ok(base);
+        }
+
+        let sub = sub.map(Box::new);
+        let sup = sup.map(Box::new);
+
+        Ok(if base.takes_limits() {
+            Node::Limits {
+                base: Box::new(base),
+                under: sub,
+                over: sup,
+            }
+        } else {
+            Node::Scripts {
+                base: Box::new(base),
+                sub,
+                sup,
+            }
+        })
+    }
+
+    /// Reads the text of a group in braces without interpreting it as math, for `\text`.
+    fn parse_raw_group(&mut self, command: &str) -> Result<String, MathError> {
+        self.skip_whitespace();
+
+        let start = self.pos;
+
+        if !self.eat('{') {
+            return Err(self.error_at(start, format!("expected `{{` after `\\{}`", command)));
+        }
+
+        let mut text = String::new();
+        let mut depth = 0usize;
+
+        loop {
+            match self.bump() {
+                None => return Err(self.error_at(start, "unmatched `{`".to_owned())),
+                Some('\\') => match self.bump() {
+                    Some(c) if !c.is_ascii_alphabetic() => text.push(c),
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    },
+                    None => (),
+                },
+                Some('{') => {
+                    depth += 1;
+                },
+                Some('}') => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                },
+                Some(c) => text.push(c),
+            }
+        }
+
+        Ok(text)
+    }
+}
+
+/// Commands for letters and symbols, and whether they are upright rather than italic.
+const IDENTS: &[(&str, &str, bool)] = &[
+    ("alpha", "\u{03B1}", false),
+    ("beta", "\u{03B2}", false),
+    ("gamma", "\u{03B3}", false),
+    ("delta", "\u{03B4}", false),
+    ("epsilon", "\u{03F5}", false),
+    ("varepsilon", "\u{03B5}", false),
+    ("zeta", "\u{03B6}", false),
+    ("eta", "\u{03B7}", false),
+    ("theta", "\u{03B8}", false),
+    ("iota", "\u{03B9}", false),
+    ("kappa", "\u{03BA}", false),
+    ("lambda", "\u{03BB}", false),
+    ("mu", "\u{03BC}", false),
+    ("nu", "\u{03BD}", false),
+    ("xi", "\u{03BE}", false),
+    ("pi", "\u{03C0}", false),
+    ("rho", "\u{03C1}", false),
+    ("sigma", "\u{03C3}", false),
+    ("tau", "\u{03C4}", false),
+    ("upsilon", "\u{03C5}", false),
+    ("phi", "\u{03D5}", false),
+    ("varphi", "\u{03C6}", false),
+    ("chi", "\u{03C7}", false),
+    ("psi", "\u{03C8}", false),
+    ("omega", "\u{03C9}", false),
+    ("Gamma", "\u{0393}", true),
+    ("Delta", "\u{0394}", true),
+    ("Theta", "\u{0398}", true),
+    ("Lambda", "\u{039B}", true),
+    ("Xi", "\u{039E}", true),
+    ("Pi", "\u{03A0}", true),
+    ("Sigma", "\u{03A3}", true),
+    ("Upsilon", "\u{03A5}", true),
+    ("Phi", "\u{03A6}", true),
+    ("Psi", "\u{03A8}", true),
+    ("Omega", "\u{03A9}", true),
+    ("infty", "\u{221E}", true),
+    ("partial", "\u{2202}", false),
+    ("nabla", "\u{2207}", true),
+    ("ell", "\u{2113}", false),
+    ("hbar", "\u{210F}", false),
+    ("emptyset", "\u{2205}", true),
+    ("%", "%", true),
+    ("#", "#", true),
+    ("$", "$", true),
+    ("_", "_", true),
+];
+
+/// Commands for brackets.
+const DELIMITERS: &[(&str, &str)] = &[
+    ("{", "{"),
+    ("}", "}"),
+    ("|", "\u{2016}"),
+    ("langle", "\u{27E8}"),
+    ("rangle", "\u{27E9}"),
+    ("lfloor", "\u{230A}"),
+    ("rfloor", "\u{230B}"),
+    ("lceil", "\u{2308}"),
+    ("rceil", "\u{2309}"),
+];
+
+const OPS: &[(&str, &str)] = &[
+    // Relations
+    ("leq", "\u{2264}"),
+    ("le", "\u{2264}"),
+    ("geq", "\u{2265}"),
+    ("ge", "\u{2265}"),
+    ("neq", "\u{2260}"),
+    ("ne", "\u{2260}"),
+    ("approx", "\u{2248}"),
+    ("equiv", "\u{2261}"),
+    ("sim", "\u{223C}"),
+    ("propto", "\u{221D}"),
+    ("ll", "\u{226A}"),
+    ("gg", "\u{226B}"),
+    ("in", "\u{2208}"),
+    ("notin", "\u{2209}"),
+    ("subset", "\u{2282}"),
+    ("subseteq", "\u{2286}"),
+    ("supset", "\u{2283}"),
+    ("supseteq", "\u{2287}"),
+    ("mid", "\u{2223}"),
+    // Arrows
+    ("to", "\u{2192}"),
+    ("rightarrow", "\u{2192}"),
+    ("leftarrow", "\u{2190}"),
+    ("leftrightarrow", "\u{2194}"),
+    ("Rightarrow", "\u{21D2}"),
+    ("Leftarrow", "\u{21D0}"),
+    ("Leftrightarrow", "\u{21D4}"),
+    ("implies", "\u{27F9}"),
+    ("iff", "\u{27FA}"),
+    ("mapsto", "\u{21A6}"),
+    // Binary operators
+    ("pm", "\u{00B1}"),
+    ("mp", "\u{2213}"),
+    ("times", "\u{00D7}"),
+    ("div", "\u{00F7}"),
+    ("cdot", "\u{22C5}"),
+    ("circ", "\u{2218}"),
+    ("oplus", "\u{2295}"),
+    ("otimes", "\u{2297}"),
+    ("cup", "\u{222A}"),
+    ("cap", "\u{2229}"),
+    ("setminus", "\u{2216}"),
+    ("wedge", "\u{2227}"),
+    ("vee", "\u{2228}"),
+    // Logic
+    ("neg", "\u{00AC}"),
+    ("forall", "\u{2200}"),
+    ("exists", "\u{2203}"),
+    // Punctuation
+    ("ldots", "\u{2026}"),
+    ("dots", "\u{2026}"),
+    ("cdots", "\u{22EF}"),
+    ("&", "&"),
+];
+
+/// Large operators whose scripts are placed above and below them in display math.
+const BIG_OPS: &[(&str, &str)] = &[
+    ("sum", "\u{2211}"),
+    ("prod", "\u{220F}"),
+    ("bigcup", "\u{22C3}"),
+    ("bigcap", "\u{22C2}"),
+];
+
+/// Integrals, whose scripts are placed to their right even in display math.
+const INTEGRALS: &[(&str, &str)] = &[
+    ("int", "\u{222B}"),
+    ("iint", "\u{222C}"),
+    ("oint", "\u{222E}"),
+];
+
+/// Functions which are written as upright words.
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan",
+    "arcsin", "arccos", "arctan",
+    "sinh", "cosh", "tanh",
+    "log", "ln", "exp",
+];
+
+/// Functions written as upright words whose scripts are placed underneath them in display math.
+const LIMIT_FUNCTIONS: &[&str] = &[
+    "lim", "max", "min", "sup", "inf", "det", "gcd",
+];
+
+const SPACES: &[(&str, &str)] = &[
+    (",", "0.1667em"),
+    (":", "0.2222em"),
+    (";", "0.2778em"),
+    ("!", "-0.1667em"),
+    (" ", "0.3333em"),
+    ("quad", "1em"),
+    ("qquad", "2em"),
+];
+
+const VARIANTS: &[(&str, Variant)] = &[
+    ("mathrm", Variant::Upright),
+    ("mathbf", Variant::Bold),
+    ("mathbb", Variant::DoubleStruck),
+];
+
+/// Accents, and whether they stretch to the width of what they are placed on.
+const ACCENTS: &[(&str, &str, bool)] = &[
+    ("hat", "^", false),
+    ("bar", "\u{00AF}", false),
+    ("vec", "\u{2192}", false),
+    ("tilde", "~", false),
+    ("dot", "\u{02D9}", false),
+    ("overline", "\u{203E}", true),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{render, MathMode, MAX_DEPTH};
+
+    fn mathml(tex: &str) -> String {
+        let html = render(tex, MathMode::Inline).unwrap().into_string();
+        let start = html.find("<mrow>").unwrap() + "<mrow>".len();
+        let end = html.rfind("</mrow><annotation").unwrap();
+        html[start..end].to_owned()
+    }
+
+    #[test]
+    fn annotation() {
+        assert_eq!(
+            render(" a<b ", MathMode::Display).unwrap().into_string(),
+            "<math display=\"block\"><semantics><mrow><mi>a</mi><mo>&lt;</mo><mi>b</mi></mrow>\
+             <annotation encoding=\"application/x-tex\">a&lt;b</annotation></semantics></math>"
+        );
+    }
+
+    #[test]
+    fn identifiers_numbers_and_operators() {
+        assert_eq!(mathml("x + 12.5"), "<mi>x</mi><mo>+</mo><mn>12.5</mn>");
+        assert_eq!(mathml("a-b*c"), "<mi>a</mi><mo>\u{2212}</mo><mi>b</mi><mo>\u{2217}</mo><mi>c</mi>");
+        assert_eq!(mathml("(x)"), "<mo stretchy=\"false\">(</mo><mi>x</mi><mo stretchy=\"false\">)</mo>");
+        assert_eq!(mathml("\\alpha\\Gamma"), "<mi>\u{03B1}</mi><mi mathvariant=\"normal\">\u{0393}</mi>");
+        assert_eq!(mathml("\\leq\\langle"), "<mo>\u{2264}</mo><mo stretchy=\"false\">\u{27E8}</mo>");
+        assert_eq!(mathml("a~b\\quad c"), "<mi>a</mi><mspace width=\"0.3333em\"></mspace><mi>b</mi><mspace width=\"1em\"></mspace><mi>c</mi>");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(mathml("\\sin x"), "<mi>sin</mi><mo>\u{2061}</mo><mi>x</mi>");
+        assert_eq!(
+            mathml("\\lim_n"),
+            "<munder><mo form=\"prefix\" movablelimits=\"true\">lim</mo><mi>n</mi></munder>"
+        );
+    }
+
+    #[test]
+    fn fractions_and_roots() {
+        assert_eq!(mathml("\\frac12"), "<mfrac><mn>1</mn><mn>2</mn></mfrac>");
+        assert_eq!(mathml("\\frac{a}{b}"), "<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>");
+        assert_eq!(mathml("\\sqrt2"), "<msqrt><mn>2</mn></msqrt>");
+        assert_eq!(mathml("\\sqrt[3]{x}"), "<mroot><mrow><mi>x</mi></mrow><mrow><mn>3</mn></mrow></mroot>");
+    }
+
+    #[test]
+    fn scripts() {
+        assert_eq!(mathml("x^2"), "<msup><mi>x</mi><mn>2</mn></msup>");
+        assert_eq!(mathml("x_i^2"), "<msubsup><mi>x</mi><mi>i</mi><mn>2</mn></msubsup>");
+        assert_eq!(mathml("x^{10}"), "<msup><mi>x</mi><mrow><mn>10</mn></mrow></msup>");
+        assert_eq!(mathml("f''"), "<msup><mi>f</mi><mo>\u{2032}\u{2032}</mo></msup>");
+        assert_eq!(mathml("{}^{14}C"), "<msup><mrow></mrow><mrow><mn>14</mn></mrow></msup><mi>C</mi>");
+        assert_eq!(mathml("\\sum_{i}"), "<munder><mo>\u{2211}</mo><mrow><mi>i</mi></mrow></munder>");
+        assert_eq!(mathml("\\int_0^1"), "<msubsup><mo>\u{222B}</mo><mn>0</mn><mn>1</mn></msubsup>");
+    }
+
+    #[test]
+    fn accents() {
+        assert_eq!(mathml("\\hat x"), "<mover accent=\"true\"><mi>x</mi><mo stretchy=\"false\">^</mo></mover>");
+        assert_eq!(
+            mathml("\\overline{z}"),
+            "<mover accent=\"true\"><mrow><mi>z</mi></mrow><mo stretchy=\"true\">\u{203E}</mo></mover>"
+        );
+    }
+
+    #[test]
+    fn text_and_variants() {
+        assert_eq!(mathml("\\text{if } x"), "<mtext>if </mtext><mi>x</mi>");
+        assert_eq!(mathml("\\mathbb{R}"), "<mrow><mi mathvariant=\"normal\">\u{211D}</mi></mrow>");
+        assert_eq!(mathml("\\mathbf v"), "<mi mathvariant=\"normal\">\u{1D42F}</mi>");
+        assert_eq!(mathml("\\mathrm{d}"), "<mrow><mi mathvariant=\"normal\">d</mi></mrow>");
+    }
+
+    #[test]
+    fn nesting() {
+        let nested = format!("{}x{}", "{".repeat(MAX_DEPTH / 2 - 1), "}".repeat(MAX_DEPTH / 2 - 1));
+        assert!(render(&nested, MathMode::Inline).is_ok());
+    }
+
+    #[test]
+    fn nested_fractions_roots_and_scripts() {
+        assert_eq!(
+            mathml("\\frac{x^2}{\\sqrt{y_1}}"),
+            "<mfrac><mrow><msup><mi>x</mi><mn>2</mn></msup></mrow>\
+             <mrow><msqrt><mrow><msub><mi>y</mi><mn>1</mn></msub></mrow></msqrt></mrow></mfrac>"
+        );
+        assert_eq!(
+            mathml("\\sqrt[n]{\\frac12}"),
+            "<mroot><mrow><mfrac><mn>1</mn><mn>2</mn></mfrac></mrow><mrow><mi>n</mi></mrow></mroot>"
+        );
+        assert_eq!(
+            mathml("x^{y^{z}}"),
+            "<msup><mi>x</mi><mrow><msup><mi>y</mi><mrow><mi>z</mi></mrow></msup></mrow></msup>"
+        );
+        assert_eq!(
+            mathml("e^{\\frac{1}{2}}"),
+            "<msup><mi>e</mi><mrow><mfrac><mrow><mn>1</mn></mrow><mrow><mn>2</mn></mrow></mfrac></mrow></msup>"
+        );
+        assert_eq!(mathml("\\sqrt{x}^2"), "<msup><msqrt><mrow><mi>x</mi></mrow></msqrt><mn>2</mn></msup>");
+        assert_eq!(
+            mathml("\\sum_{i=1}^{n} x_i^2"),
+            "<munderover><mo>\u{2211}</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mrow><mi>n</mi></mrow></munderover>\
+             <msubsup><mi>x</mi><mi>i</mi><mn>2</mn></msubsup>"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let too_deep = "{".repeat(MAX_DEPTH);
+
+        let cases: &[(&str, &str, std::ops::Range<usize>)] = &[
+            ("a}", "unmatched `}`", 1..2),
+            ("{}}", "unmatched `}`", 2..3),
+            ("{x", "unmatched `{`", 0..2),
+            ("\\frac{a}{b", "unmatched `{`", 8..10),
+            ("\\sqrt[3]{x", "unmatched `{`", 8..10),
+            ("x^{y^{z}", "unmatched `{`", 2..8),
+            ("\\sqrt[3}", "unmatched `[`", 5..8),
+            ("\\frac1", "expected an argument, found the end of the math", 6..6),
+            ("x^{2", "unmatched `{`", 2..4),
+            ("x^^", "unexpected `^`", 2..3),
+            ("a & b", "unexpected `&`", 2..3),
+            ("#", "unexpected `#`", 0..1),
+            ("50%", "unexpected `%`; write `\\%` for a percent sign", 2..3),
+            ("a\\", "expected a command name after `\\`", 1..2),
+            ("\\sqrt[3 x", "unmatched `[`", 5..9),
+            ("x^1^2", "double superscript; use braces to group the superscript", 3..4),
+            ("x_1_2", "double subscript; use braces to group the subscript", 3..4),
+            ("x^2'", "prime after a superscript; write the prime first", 3..4),
+            ("\\text x", "expected `{` after `\\text`", 6..7),
+            ("\\text{x", "unmatched `{`", 5..7),
+            (&too_deep, "math is nested too deeply", MAX_DEPTH / 2..MAX_DEPTH / 2 + 1),
+        ];
+
+        for (tex, message, span) in cases {
+            let err = match render(tex, MathMode::Inline) {
+                Ok(_) => panic!("no error for `{}`", tex),
+                Err(err) => err,
+            };
+            assert_eq!((err.message(), err.span()), (*message, span.clone()), "wrong error for `{}`", tex);
+        }
+    }
+
+    #[test]
+    fn unsupported_commands() {
+        let cases: &[(&str, &str, std::ops::Range<usize>)] = &[
+            ("\\foo x", "\\foo", 0..4),
+            ("a \\\\ b", "\\\\", 2..4),
+            ("\\left( x \\right)", "\\left", 0..5),
+            ("\\begin{aligned} a \\end{aligned}", "\\begin", 0..6),
+            ("\\binom nk", "\\binom", 0..6),
+            ("\\mathcal{L}", "\\mathcal", 0..8),
+            ("\\frac{\\foo}{x}", "\\foo", 6..10),
+            ("x_{\\baz}", "\\baz", 3..7),
+            ("\\sqrt[\\qux]{x}", "\\qux", 6..10),
+        ];
+
+        for (tex, command, span) in cases {
+            let err = render(tex, MathMode::Inline).unwrap_err();
+            let message = format!("unsupported command `{}`", command);
+            assert_eq!((err.message(), err.span()), (message.as_str(), span.clone()), "wrong error for `{}`", tex);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let cases = [
+            "{".repeat(100_000),
+            "\\sqrt".repeat(100_000),
+            "\\hat".repeat(100_000),
+            "\\frac{".repeat(100_000),
+            "\\sqrt[".repeat(100_000),
+            "x^{".repeat(100_000),
+        ];
+
+        for tex in cases {
+            let err = render(&tex, MathMode::Inline).unwrap_err();
+            assert_eq!(err.message(), "math is nested too deeply");
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use std::fmt;
+
+use serde::{
+    de::{self, Visitor},
+    forward_to_deserialize_any,
+    Deserialize,
+    Deserializer,
+};
+
+/// A problem found in a post's source, with enough information to point the author at the part
+/// of the file which caused it.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    span: Option<Span>,
+    hint: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    /// A problem the author must fix, such as a header which cannot be parsed or a broken link.
+    Error,
+    /// The post was parsed, but part of it was ignored or is likely to be a mistake.
+    Warning,
+}
+
+/// The position of a diagnostic in the source file. Lines and columns start at 1.
+#[derive(Clone, Debug)]
+struct Span {
+    line: usize,
+    column: usize,
+    len: usize,
+    snippet: String,
+}
+
+impl Diagnostic {
+    pub(super) fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            span: None,
+            hint: None,
+        }
+    }
+
+    pub(super) fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(message)
+        }
+    }
+
+    /// Points the diagnostic at `len` characters starting at the given line and column of
+    /// `source`, both of which start at 1. The line of `source` is kept as the snippet.
+    pub(super) fn at(mut self, source: &str, line: usize, column: usize, len: usize) -> Self {
+        let snippet = source
+            .lines()
+            .nth(line.saturating_sub(1))
+            .unwrap_or_default()
+            .to_owned();
+
+        self.span = Some(Span {
+            line,
+            column,
+            len,
+            snippet,
+        });
+
+        self
+    }
+
+    pub(super) fn with_hint(mut self, hint: String) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    /// Moves the diagnostic down by the given number of lines. This is used to make positions
+    /// relative to the start of the file rather than the start of the header.
+    pub(super) fn offset_lines(mut self, lines: usize) -> Self {
+        if let Some(span) = self.span.as_mut() {
+            span.line += lines;
+        }
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn line(&self) -> Option<usize> {
+        self.span.as_ref().map(|span| span.line)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn column(&self) -> Option<usize> {
+        self.span.as_ref().map(|span| span.column)
+    }
+
+    /// Returns the line of the source file that the diagnostic points at.
+    #[inline]
+    #[must_use]
+    pub fn snippet(&self) -> Option<&str> {
+        self.span.as_ref().map(|span| span.snippet.as_str())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    /// Returns a multi-line report of the diagnostic for the file at the given path, in the style
+    /// of compiler error messages.
+    #[must_use]
+    pub fn report<'a>(&'a self, path: &'a str) -> Report<'a> {
+        Report {
+            diagnostic: self,
+            path,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(span) = &self.span {
+            write!(f, " at line {} column {}", span.line, span.column)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Report<'a> {
+    diagnostic: &'a Diagnostic,
+    path: &'a str,
+}
+
+impl<'a> fmt::Display for Report<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        writeln!(f, "{}: {}", severity, self.diagnostic.message)?;
+
+        match &self.diagnostic.span {
+            Some(span) => {
+                let gutter = " ".repeat(span.line.to_string().len());
+                writeln!(f, "{}--> {}:{}:{}", gutter, self.path, span.line, span.column)?;
+                writeln!(f, "{} |", gutter)?;
+                writeln!(f, "{} | {}", span.line, span.snippet)?;
+                writeln!(
+                    f,
+                    "{} | {}{}",
+                    gutter,
+                    " ".repeat(span.column.saturating_sub(1)),
+                    "^".repeat(span.len.max(1))
+                )?;
+                if let Some(hint) = &self.diagnostic.hint {
+                    writeln!(f, "{} = hint: {}", gutter, hint)?;
+                }
+            },
+            None => {
+                writeln!(f, "--> {}", self.path)?;
+                if let Some(hint) = &self.diagnostic.hint {
+                    writeln!(f, "= hint: {}", hint)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates a diagnostic for an error which occurred while parsing a TOML header.
+pub(super) fn from_toml_error(header: &str, err: &toml::de::Error) -> Diagnostic {
+    let message = err.to_string();
+
+    match err.line_col() {
+        // The error message ends with the position, which we report separately.
+        Some((line, column)) => {
+            let message = message
+                .rsplit_once(" at line ")
+                .map_or(message.as_str(), |(message, _)| message)
+                .to_owned();
+            Diagnostic::error(message).at(header, line + 1, column + 1, 1)
+        },
+        None => Diagnostic::error(message),
+    }
+}
+
+/// Creates a diagnostic for an error which occurred while parsing a YAML header.
+pub(super) fn from_yaml_error(header: &str, err: &serde_yaml::Error) -> Diagnostic {
+    let message = err.to_string();
+
+    match err.location() {
+        Some(location) => {
+            let message = message
+                .rsplit_once(" at line ")
+                .map_or(message.as_str(), |(message, _)| message)
+                .to_owned();
+            Diagnostic::error(message).at(header, location.line(), location.column(), 1)
+        },
+        None => Diagnostic::error(message),
+    }
+}
+
+/// Creates a warning for a header key which was not recognised and so was ignored. `path` is the
+/// path of the key, with the names of nested keys separated by dots, and `known_keys` are the
+/// keys which would have been recognised in its place.
+pub(super) fn unknown_key(header: &str, path: &str, known_keys: &[&str]) -> Diagnostic {
+    let key = path.rsplit('.').next().unwrap_or(path);
+
+    let mut diagnostic = Diagnostic::warning(format!("unknown header key `{}` will be ignored", path));
+
+    if let Some((line, column)) = find_key(header, key) {
+        diagnostic = diagnostic.at(header, line, column, key.chars().count());
+    }
+
+    let suggestion = known_keys
+        .iter()
+        .map(|known_key| (known_key, edit_distance(key, known_key)))
+        .filter(|(known_key, distance)| *distance <= (known_key.len() / 3).max(1))
+        .min_by_key(|(_, distance)| *distance);
+
+    if let Some((known_key, _)) = suggestion {
+        diagnostic = diagnostic.with_hint(format!("did you mean `{}`?", known_key));
+    }
+
+    diagnostic
+}
+
+/// Finds the line and column where the given key is assigned a value in a TOML or YAML header.
+fn find_key(header: &str, key: &str) -> Option<(usize, usize)> {
+    header
+        .lines()
+        .enumerate()
+        // Both TOML and YAML comments start with `#`.
+        .filter(|(_, line)| !line.trim_start().starts_with('#'))
+        .find_map(|(i, line)| {
+            line.match_indices(key)
+                .find(|(start, _)| {
+                    let before = line[..*start].chars().next_back();
+                    let after = line[start + key.len()..].trim_start().chars().next();
+                    !matches!(before, Some(c) if c.is_alphanumeric() || c == '_' || c == '-')
+                        && matches!(after, Some('=' | ':'))
+                })
+                .map(|(start, _)| (i + 1, line[..start].chars().count() + 1))
+        })
+}
+
+/// The number of single-character insertions, deletions and substitutions needed to turn one
+/// string into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut row = (0..=b.len()).collect::<Vec<usize>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Returns the names of the fields of a struct which derives `Deserialize`. The derived
+/// implementation passes its field names to `deserialize_struct`, so we use a deserializer which
+/// records them and then gives up.
+pub(super) fn struct_fields<'de, T>() -> &'static [&'static str]
+where
+    T: Deserialize<'de>,
+{
+    struct FieldsDeserializer<'a> {
+        fields: &'a mut &'static [&'static str],
+    }
+
+    impl<'de, 'a> Deserializer<'de> for FieldsDeserializer<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            *self.fields = fields;
+            Err(de::Error::custom("finished reading fields"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldsDeserializer { fields: &mut fields });
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::post::header::Header;
+
+    use super::{edit_distance, find_key, struct_fields};
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("title", "title"), 0);
+        assert_eq!(edit_distance("", "tags"), 4);
+        assert_eq!(edit_distance("tags", ""), 4);
+        assert_eq!(edit_distance("tite", "title"), 1);
+        assert_eq!(edit_distance("titlee", "title"), 1);
+        assert_eq!(edit_distance("titel", "title"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn find_key_in_toml_and_yaml() {
+        let toml = "# title = \"Old\"\ntitle = \"T\"\nauthor_name = \"a\"\n\n[series]\n  name  = \"s\"\n";
+        assert_eq!(find_key(toml, "title"), Some((2, 1)));
+        assert_eq!(find_key(toml, "name"), Some((6, 3)));
+        assert_eq!(find_key(toml, "series"), None);
+        assert_eq!(find_key(toml, "tags"), None);
+
+        let yaml = "title: T\nséries: x\nseries:\n  name: s\n";
+        assert_eq!(find_key(yaml, "title"), Some((1, 1)));
+        assert_eq!(find_key(yaml, "name"), Some((4, 3)));
+        assert_eq!(find_key(yaml, "ries"), None);
+    }
+
+    #[test]
+    fn struct_fields_of_derived_structs() {
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Fields {
+            first: u8,
+            #[serde(rename = "second-field")]
+            second: Option<String>,
+        }
+
+        assert_eq!(struct_fields::<Fields>(), ["first", "second-field"]);
+        assert!(struct_fields::<Header>().contains(&"title"));
+        assert!(struct_fields::<u8>().is_empty());
+    }
+}
@@ -1,20 +1,33 @@
 use std::{error, fmt};
 
+use super::diagnostic::Diagnostic;
+
 #[derive(Debug)]
 pub enum Error {
     NoDelim,
-    Header(Box<toml::de::Error>),
+    Header(Box<Diagnostic>),
     IdTooLong(usize),
 }
 
+impl Error {
+    /// Returns a diagnostic describing the error, if it was caused by an invalid header.
+    #[must_use]
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            Self::Header(diagnostic) => Some(diagnostic),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::NoDelim => {
                 write!(f, "post has no header; no delimiter `\\n---\\n` found")
             },
-            Self::Header(err) => {
-                write!(f, "error decoding post header: {}", err)
+            Self::Header(diagnostic) => {
+                write!(f, "error decoding post header: {}", diagnostic)
             },
             Self::IdTooLong(len) => {
                 write!(f, "post id too long: {} bytes", len)
@@ -24,9 +37,3 @@ impl fmt::Display for Error {
 }
 
 impl error::Error for Error {}
-
-impl From<toml::de::Error> for Error {
-    fn from(err: toml::de::Error) -> Self {
-        Self::Header(Box::new(err))
-    }
-}
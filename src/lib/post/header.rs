@@ -1,19 +1,76 @@
 use std::{fmt, str};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use libshire::strings::ShString22;
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
 
-use super::error::Error;
+use super::{diagnostic::{self, Diagnostic}, error::Error, id::Id};
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct Header {
     pub(super) title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(super) subtitle: Option<String>,
-    pub(super) author: ShString22,
-    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) lang: Option<ShString22>,
+    #[serde(default, deserialize_with = "deserialize_canonical_url", skip_serializing_if = "Option::is_none")]
+    pub(super) canonical_url: Option<String>,
+    // Posts imported from other static site generators often leave out the author, so it may be
+    // filled in from a default when the post is rendered.
+    #[serde(
+        rename = "author",
+        alias = "authors",
+        default,
+        deserialize_with = "deserialize_one_or_many",
+        serialize_with = "serialize_one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub(super) authors: Vec<ShString22>,
+    #[serde(default, deserialize_with = "deserialize_tags")]
     pub(super) tags: Vec<ShString22>,
+    #[serde(default, deserialize_with = "deserialize_aliases", skip_serializing_if = "Vec::is_empty")]
+    pub(super) aliases: Vec<ShString22>,
+    // Jekyll and Hugo front matter calls these `date` and `lastmod`.
+    #[serde(
+        alias = "date",
+        default,
+        deserialize_with = "deserialize_opt_date_time",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub(super) published: Option<DateTime<Utc>>,
+    #[serde(
+        alias = "lastmod",
+        default,
+        deserialize_with = "deserialize_opt_date_time",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(super) updated: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) toc: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(super) draft: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(super) unlisted: bool,
+    #[serde(default, alias = "featured", skip_serializing_if = "is_false")]
+    pub(super) pinned: bool,
+    // Not called `layout`, since Jekyll front matter uses that key for its own templates.
+    #[serde(rename = "post_layout", skip_serializing_if = "Option::is_none")]
+    pub(super) layout: Option<Layout>,
+    // Tables must come after all of the plain values, or `toml` fails to serialise the header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) series: Option<Series>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) cover: Option<Cover>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) robots: Option<Robots>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) styles: Vec<Asset>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) scripts: Vec<Asset>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) changelog: Vec<ChangelogEntry>,
 }
 
 impl Header {
@@ -43,14 +100,57 @@ impl Header {
 
     #[inline]
     #[must_use]
-    pub fn author(&self) -> &str {
-        &self.author
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
     }
 
     #[inline]
     #[must_use]
-    pub fn author_mut(&mut self) -> &mut ShString22 {
-        &mut self.author
+    pub fn summary_mut(&mut self) -> &mut Option<String> {
+        &mut self.summary
+    }
+
+    /// Returns the language the post is written in, as a BCP 47 language tag such as `en` or
+    /// `de-AT`. If this is `None`, the post is in the site's default language.
+    #[inline]
+    #[must_use]
+    pub fn lang(&self) -> Option<&str> {
+        self.lang.as_deref()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn lang_mut(&mut self) -> &mut Option<ShString22> {
+        &mut self.lang
+    }
+
+    /// Returns the URL where the post was originally published, if it was first published
+    /// somewhere other than this site.
+    #[inline]
+    #[must_use]
+    pub fn canonical_url(&self) -> Option<&str> {
+        self.canonical_url.as_deref()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn canonical_url_mut(&mut self) -> &mut Option<String> {
+        &mut self.canonical_url
+    }
+
+    /// Returns the authors of the post. Each author is either the key of an author in the site's
+    /// author registry, or the name of an author who is not in the registry. This is empty if the
+    /// header does not name any authors.
+    #[inline]
+    #[must_use]
+    pub fn authors(&self) -> &[ShString22] {
+        &self.authors
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn authors_mut(&mut self) -> &mut Vec<ShString22> {
+        &mut self.authors
     }
 
     #[inline]
@@ -65,6 +165,19 @@ impl Header {
         &mut self.tags
     }
 
+    /// Returns the old IDs of the post, which should redirect to the post's current ID.
+    #[inline]
+    #[must_use]
+    pub fn aliases(&self) -> &[ShString22] {
+        &self.aliases
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn aliases_mut(&mut self) -> &mut Vec<ShString22> {
+        &mut self.aliases
+    }
+
     #[inline]
     #[must_use]
     pub fn published(&self) -> Option<DateTime<Utc>> {
@@ -76,14 +189,440 @@ impl Header {
     pub fn published_mut(&mut self) -> &mut Option<DateTime<Utc>> {
         &mut self.published
     }
+
+    #[inline]
+    #[must_use]
+    pub fn updated(&self) -> Option<DateTime<Utc>> {
+        self.updated
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn updated_mut(&mut self) -> &mut Option<DateTime<Utc>> {
+        &mut self.updated
+    }
+
+    /// Returns whether a table of contents should be shown for the post. If this is `None`, the
+    /// table of contents is shown if the post is sufficiently long.
+    #[inline]
+    #[must_use]
+    pub fn toc(&self) -> Option<bool> {
+        self.toc
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn toc_mut(&mut self) -> &mut Option<bool> {
+        &mut self.toc
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn draft(&self) -> bool {
+        self.draft
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn draft_mut(&mut self) -> &mut bool {
+        &mut self.draft
+    }
+
+    /// Returns whether the post is published at the given time, rather than being a draft or being
+    /// scheduled to be published later. Unlisted posts count as published.
+    #[must_use]
+    pub fn is_published_at(&self, time: DateTime<Utc>) -> bool {
+        match self.published {
+            _ if self.draft => false,
+            Some(published) => published <= time,
+            None => true,
+        }
+    }
+
+    /// Returns the time by which the posts this post links to must be published, so that it does
+    /// not link to posts which readers cannot see yet: its publish date if it is scheduled, or the
+    /// given time if it is already published. Drafts can link to any post, so this is `None` for
+    /// them.
+    #[must_use]
+    pub fn links_published_by(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.published {
+            _ if self.draft => None,
+            Some(published) => Some(published.max(now)),
+            None => Some(now),
+        }
+    }
+
+    /// Returns whether the post is unlisted. Unlisted posts can be viewed by anyone who has the
+    /// link, but do not appear in any listings or feeds.
+    #[inline]
+    #[must_use]
+    pub fn unlisted(&self) -> bool {
+        self.unlisted
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn unlisted_mut(&mut self) -> &mut bool {
+        &mut self.unlisted
+    }
+
+    /// Returns whether the post is pinned, meaning it is shown on the homepage ahead of the most
+    /// recent posts.
+    #[inline]
+    #[must_use]
+    pub fn pinned(&self) -> bool {
+        self.pinned
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn pinned_mut(&mut self) -> &mut bool {
+        &mut self.pinned
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn series(&self) -> Option<&Series> {
+        self.series.as_ref()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn series_mut(&mut self) -> &mut Option<Series> {
+        &mut self.series
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn cover(&self) -> Option<&Cover> {
+        self.cover.as_ref()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn cover_mut(&mut self) -> &mut Option<Cover> {
+        &mut self.cover
+    }
+
+    /// Returns the instructions for search engine crawlers given in the header, which override
+    /// the site's defaults for this post.
+    #[inline]
+    #[must_use]
+    pub fn robots(&self) -> Option<&Robots> {
+        self.robots.as_ref()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn robots_mut(&mut self) -> &mut Option<Robots> {
+        &mut self.robots
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn layout(&self) -> Layout {
+        self.layout.unwrap_or(Layout::Standard)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn layout_mut(&mut self) -> &mut Option<Layout> {
+        &mut self.layout
+    }
+
+    /// Returns the extra stylesheets to include in the post's page, in the order they should be
+    /// included.
+    #[inline]
+    #[must_use]
+    pub fn styles(&self) -> &[Asset] {
+        &self.styles
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn styles_mut(&mut self) -> &mut Vec<Asset> {
+        &mut self.styles
+    }
+
+    /// Returns the extra scripts to include in the post's page, in the order they should be run.
+    #[inline]
+    #[must_use]
+    pub fn scripts(&self) -> &[Asset] {
+        &self.scripts
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn scripts_mut(&mut self) -> &mut Vec<Asset> {
+        &mut self.scripts
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn changelog(&self) -> &[ChangelogEntry] {
+        &self.changelog
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn changelog_mut(&mut self) -> &mut Vec<ChangelogEntry> {
+        &mut self.changelog
+    }
+}
+
+/// The series of posts that a post belongs to. Posts in a series are ordered by their `part`
+/// number; posts without a part number come after all of the numbered parts, in the order they
+/// were published.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct Series {
+    name: ShString22,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part: Option<u32>,
+}
+
+impl Series {
+    #[inline]
+    #[must_use]
+    pub fn new(name: ShString22, part: Option<u32>) -> Self {
+        Self { name, part }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> &ShString22 {
+        &self.name
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn part(&self) -> Option<u32> {
+        self.part
+    }
+}
+
+/// A lead image for a post. The `path` is relative to the post media directory.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct Cover {
+    #[serde(deserialize_with = "deserialize_relative_path")]
+    path: String,
+    alt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caption: Option<String>,
+}
+
+impl Cover {
+    #[inline]
+    #[must_use]
+    pub fn new(path: String, alt: String, caption: Option<String>) -> Self {
+        Self { path, alt, caption }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn alt(&self) -> &str {
+        &self.alt
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn caption(&self) -> Option<&str> {
+        self.caption.as_deref()
+    }
+
+    /// Guesses the MIME type of the image from the extension of its path.
+    #[must_use]
+    pub fn mime_type(&self) -> Option<&'static str> {
+        let (_, extension) = self.path.rsplit_once('.')?;
+
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some("image/png"),
+            "jpg" | "jpeg" => Some("image/jpeg"),
+            "gif" => Some("image/gif"),
+            "webp" => Some("image/webp"),
+            "avif" => Some("image/avif"),
+            "svg" => Some("image/svg+xml"),
+            _ => None,
+        }
+    }
+}
+
+/// Instructions for search engine crawlers. Each flag which is `None` is left at the site's
+/// default for the post.
+#[derive(Clone, Default, Deserialize, Serialize, Debug)]
+pub struct Robots {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    follow: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_index: Option<bool>,
+}
+
+impl Robots {
+    /// Whether the post may be shown in search results.
+    #[inline]
+    #[must_use]
+    pub fn index(&self) -> Option<bool> {
+        self.index
+    }
+
+    /// Whether the links in the post may be followed.
+    #[inline]
+    #[must_use]
+    pub fn follow(&self) -> Option<bool> {
+        self.follow
+    }
+
+    /// Whether a cached copy of the post may be shown in search results.
+    #[inline]
+    #[must_use]
+    pub fn archive(&self) -> Option<bool> {
+        self.archive
+    }
+
+    /// Whether a snippet of the post may be shown in search results.
+    #[inline]
+    #[must_use]
+    pub fn snippet(&self) -> Option<bool> {
+        self.snippet
+    }
+
+    /// Whether the images in the post may be indexed.
+    #[inline]
+    #[must_use]
+    pub fn image_index(&self) -> Option<bool> {
+        self.image_index
+    }
+}
+
+/// The page layout used to display a post.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// The site's usual single narrow column.
+    Standard,
+    /// A wider column, for posts with large tables, diagrams or interactive demos.
+    Wide,
+}
+
+/// An extra stylesheet or script to include in a post's page. The `path` is relative to the
+/// directory given by `root`, which is the post media directory unless stated otherwise.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct Asset {
+    #[serde(deserialize_with = "deserialize_relative_path")]
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root: Option<AssetRoot>,
+}
+
+impl Asset {
+    #[inline]
+    #[must_use]
+    pub fn new(path: String, root: Option<AssetRoot>) -> Self {
+        Self { path, root }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn root(&self) -> AssetRoot {
+        self.root.unwrap_or(AssetRoot::Media)
+    }
+}
+
+/// The directory an asset's path is relative to.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetRoot {
+    /// The post media directory, for assets which belong to a single post.
+    Media,
+    /// The static content directory, for assets shared between posts.
+    Static,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ChangelogEntry {
+    #[serde(deserialize_with = "deserialize_date_time")]
+    date: DateTime<Utc>,
+    note: String,
+}
+
+impl ChangelogEntry {
+    #[inline]
+    #[must_use]
+    pub fn new(date: DateTime<Utc>, note: String) -> Self {
+        Self { date, note }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn date(&self) -> DateTime<Utc> {
+        self.date
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn note(&self) -> &str {
+        &self.note
+    }
 }
 
 impl str::FromStr for Header {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        toml::from_str(s)
-            .map_err(Error::from)
+        Self::parse_toml(s)
+            .map(|(header, _)| header)
+            .map_err(|diagnostic| Error::Header(Box::new(diagnostic)))
+    }
+}
+
+impl Header {
+    /// Parses a header written in YAML, as used in the front matter of posts written for static
+    /// site generators such as Jekyll and Hugo.
+    pub fn from_yaml_str(s: &str) -> Result<Self, Error> {
+        Self::parse_yaml(s)
+            .map(|(header, _)| header)
+            .map_err(|diagnostic| Error::Header(Box::new(diagnostic)))
+    }
+
+    /// Parses a TOML header, returning warnings for any keys which were not recognised. The
+    /// positions of diagnostics are relative to the start of the header.
+    pub(super) fn parse_toml(s: &str) -> Result<(Self, Vec<Diagnostic>), Diagnostic> {
+        let mut ignored = Vec::new();
+        let mut deserializer = toml::Deserializer::new(s);
+
+        let header = serde_ignored::deserialize(&mut deserializer, |path| ignored.push(path.to_string()))
+            .map_err(|err| diagnostic::from_toml_error(s, &err))?;
+
+        Ok((header, unknown_key_warnings(s, &ignored)))
+    }
+
+    /// Parses a YAML header, returning warnings for any keys which were not recognised. The
+    /// positions of diagnostics are relative to the start of the header.
+    pub(super) fn parse_yaml(s: &str) -> Result<(Self, Vec<Diagnostic>), Diagnostic> {
+        let mut ignored = Vec::new();
+        let deserializer = serde_yaml::Deserializer::from_str(s);
+
+        let header = serde_ignored::deserialize(deserializer, |path| ignored.push(path.to_string()))
+            .map_err(|err| diagnostic::from_yaml_error(s, &err))?;
+
+        Ok((header, unknown_key_warnings(s, &ignored)))
     }
 }
 
@@ -94,3 +633,319 @@ impl fmt::Display for Header {
             .and_then(|s| f.write_str(s.trim()))
     }
 }
+
+fn unknown_key_warnings(header: &str, ignored: &[String]) -> Vec<Diagnostic> {
+    ignored
+        .iter()
+        .map(|path| {
+            // `serde_ignored` writes a `?` in the path for each `Option` the key is inside of.
+            let path = path
+                .split('.')
+                .filter(|segment| *segment != "?")
+                .collect::<Vec<_>>()
+                .join(".");
+
+            // Suggest the keys of the table which the unknown key was found in.
+            let known_keys = match path.split_once('.') {
+                None => diagnostic::struct_fields::<Header>(),
+                Some(("series", _)) => diagnostic::struct_fields::<Series>(),
+                Some(("cover", _)) => diagnostic::struct_fields::<Cover>(),
+                Some(("robots", _)) => diagnostic::struct_fields::<Robots>(),
+                Some(("styles" | "scripts", _)) => diagnostic::struct_fields::<Asset>(),
+                Some(("changelog", _)) => diagnostic::struct_fields::<ChangelogEntry>(),
+                Some(_) => &[],
+            };
+
+            diagnostic::unknown_key(header, &path, known_keys)
+        })
+        .collect()
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// Deserializes either a single value or a list of values, so that a header can contain either
+/// `author = "a"` or `author = ["a", "b"]`.
+fn deserialize_one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    OneOrMany::deserialize(deserializer)
+        .map(|values| match values {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        })
+}
+
+/// Deserializes a canonical URL, which must be an absolute `http` or `https` URL since it is used
+/// as is in the post's page and in feeds.
+fn deserialize_canonical_url<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let url = match Option::<String>::deserialize(deserializer)? {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+
+    let host = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .and_then(|rest| rest.split(['/', '?', '#']).next())
+        .unwrap_or_default();
+
+    if host.is_empty() || url.contains(char::is_whitespace) {
+        Err(serde::de::Error::custom(format!(
+            "invalid canonical URL `{}`: expected an absolute URL such as `https://example.com/post`",
+            url
+        )))
+    } else {
+        Ok(Some(url))
+    }
+}
+
+/// Deserializes the path of a file relative to the directory it is served from, rejecting any
+/// path with `.` or `..` segments which could refer to a file outside of that directory.
+fn deserialize_relative_path<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let path = String::deserialize(deserializer)?;
+
+    if path.split(['/', '\\']).any(|segment| segment == "." || segment == "..") {
+        Err(serde::de::Error::custom(format!(
+            "invalid path `{}`: paths cannot contain `.` or `..` segments",
+            path
+        )))
+    } else {
+        Ok(path)
+    }
+}
+
+/// Deserializes a list of tags, which may also be written as a single string of tags separated by
+/// whitespace, as in Jekyll front matter.
+fn deserialize_tags<'de, D>(deserializer: D) -> Result<Vec<ShString22>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Tags {
+        Spaced(String),
+        List(Vec<ShString22>),
+    }
+
+    Tags::deserialize(deserializer)
+        .map(|tags| match tags {
+            Tags::Spaced(tags) => tags.split_whitespace().map(ShString22::new_from_str).collect(),
+            Tags::List(tags) => tags,
+        })
+}
+
+/// Deserializes a date and time written in RFC 3339 format, or a date on its own such as
+/// `2022-06-01`, which is taken to be midnight UTC.
+fn deserialize_date_time<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_date_time(&s).ok_or_else(|| invalid_date(&s))
+}
+
+/// Deserializes an optional date, in any of the formats accepted by `deserialize_date_time`.
+fn deserialize_opt_date_time<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| parse_date_time(&s).ok_or_else(|| invalid_date(&s)))
+        .transpose()
+}
+
+fn invalid_date<E: serde::de::Error>(s: &str) -> E {
+    E::custom(format!(
+        "invalid date `{}`: expected a date such as `2022-06-01` or a date and time such as `2022-06-01T12:00:00Z`",
+        s
+    ))
+}
+
+fn parse_date_time(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(s) {
+        return Some(date_time.with_timezone(&Utc));
+    }
+
+    // Jekyll writes dates and times like `2022-06-01 12:00:00 +0100`.
+    if let Ok(date_time) = DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S %z") {
+        return Some(date_time.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .map(|date| DateTime::from_utc(date.and_hms(0, 0, 0), Utc))
+}
+
+/// Deserializes a list of aliases, rejecting any which could not be used as a post ID.
+fn deserialize_aliases<'de, D>(deserializer: D) -> Result<Vec<ShString22>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let aliases = Vec::<ShString22>::deserialize(deserializer)?;
+
+    match aliases.iter().find(|alias| Id::new(alias).is_none()) {
+        Some(alias) => Err(serde::de::Error::custom(format!(
+            "invalid alias `{}`: aliases must be non-empty and cannot contain `/`, `\\` or `.`",
+            alias
+        ))),
+        None => Ok(aliases),
+    }
+}
+
+/// Serializes a list containing exactly one value as that value, and any other list as a list.
+fn serialize_one_or_many<S, T>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    match values {
+        [value] => value.serialize(serializer),
+        values => values.serialize(serializer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libshire::strings::ShString22;
+
+    use crate::test_util::toml_header;
+
+    use super::{Header, Layout};
+
+    fn header(extra: &str) -> Header {
+        toml_header(extra).parse().unwrap()
+    }
+
+    fn strs(values: &[ShString22]) -> Vec<&str> {
+        values.iter().map(|value| &**value).collect()
+    }
+
+    #[test]
+    fn display_with_layout_and_tables() {
+        let header = header("post_layout = \"wide\"\n\n[series]\nname = \"s\"\n\n[robots]\nindex = false");
+
+        let displayed = header.to_string();
+        assert!(displayed.contains("post_layout = 'wide'"));
+        assert!(displayed.parse::<Header>().is_ok());
+    }
+
+    #[test]
+    fn is_published_at() {
+        let now = "2022-06-01T12:00:00Z".parse().unwrap();
+
+        assert!(header("").is_published_at(now));
+        assert!(header("published = \"2022-05-01T12:00:00Z\"").is_published_at(now));
+        assert!(!header("published = \"2022-07-01T12:00:00Z\"").is_published_at(now));
+        assert!(!header("draft = true").is_published_at(now));
+    }
+
+    #[test]
+    fn links_published_by() {
+        let now = "2022-06-01T12:00:00Z".parse().unwrap();
+
+        assert_eq!(header("").links_published_by(now), Some(now));
+        assert_eq!(header("published = \"2022-05-01T12:00:00Z\"").links_published_by(now), Some(now));
+        assert_eq!(
+            header("published = \"2022-07-01T12:00:00Z\"").links_published_by(now),
+            Some("2022-07-01T12:00:00Z".parse().unwrap())
+        );
+        assert_eq!(header("published = \"2022-07-01T12:00:00Z\"\ndraft = true").links_published_by(now), None);
+    }
+
+    #[test]
+    fn invalid_alias() {
+        assert!(toml_header("aliases = [\"old-name\"]").parse::<Header>().is_ok());
+        assert!(toml_header("aliases = [\"old/name\"]").parse::<Header>().is_err());
+        assert!(toml_header("aliases = [\"\"]").parse::<Header>().is_err());
+    }
+
+    #[test]
+    fn cover_path_outside_media_dir() {
+        let header = |path: &str| toml_header(&format!("\n[cover]\npath = \"{}\"\nalt = \"\"", path)).parse::<Header>();
+
+        assert!(header("images/cover.png").is_ok());
+        assert!(header("images/../cover.png").is_err());
+        assert!(header("../config.toml").is_err());
+        assert!(header("./cover.png").is_err());
+    }
+
+    #[test]
+    fn canonical_url() {
+        let header = |url: &str| toml_header(&format!("canonical_url = \"{}\"", url)).parse::<Header>();
+
+        assert_eq!(header("https://example.com/post").unwrap().canonical_url(), Some("https://example.com/post"));
+        assert!(header("http://example.com").is_ok());
+        assert!(header("/articles/post").is_err());
+        assert!(header("example.com/post").is_err());
+        assert!(header("https:///post").is_err());
+        assert!(header("ftp://example.com/post").is_err());
+        assert!(header("https://example.com/a post").is_err());
+    }
+
+    #[test]
+    fn jekyll_front_matter() {
+        let header = Header::from_yaml_str(
+            "layout: post\ntitle: Title\ndate: 2021-03-04\nlastmod: 2021-03-05 10:30:00 +0100\ntags: rust  web\n"
+        ).unwrap();
+
+        assert!(header.authors().is_empty());
+        assert_eq!(header.published().unwrap().to_rfc3339(), "2021-03-04T00:00:00+00:00");
+        assert_eq!(header.updated().unwrap().to_rfc3339(), "2021-03-05T09:30:00+00:00");
+        assert_eq!(strs(header.tags()), ["rust", "web"]);
+    }
+
+    #[test]
+    fn jekyll_layout_is_ignored() {
+        let (header, warnings) = Header::parse_yaml("layout: post\ntitle: Title\npost_layout: wide\n").unwrap();
+
+        assert_eq!(header.layout(), Layout::Wide);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message().contains("`layout`"));
+    }
+
+    #[test]
+    fn hugo_front_matter() {
+        let header = Header::from_yaml_str(
+            "title: Title\nauthor: me\ndate: 2021-03-04T12:00:00Z\ntags:\n  - rust\n  - web\n"
+        ).unwrap();
+
+        assert_eq!(strs(header.authors()), ["me"]);
+        assert_eq!(header.published().unwrap().to_rfc3339(), "2021-03-04T12:00:00+00:00");
+        assert_eq!(strs(header.tags()), ["rust", "web"]);
+    }
+
+    #[test]
+    fn invalid_date() {
+        assert!(Header::from_yaml_str("title: Title\ndate: 4th March 2021\n").is_err());
+        assert!(Header::from_yaml_str("title: Title\ndate: 2021-03-04T12:00:00\n").is_err());
+    }
+
+    #[test]
+    fn changelog_dates() {
+        let header = header(
+            "\n[[changelog]]\ndate = \"2022-06-01\"\nnote = \"a\"\n\n[[changelog]]\ndate = \"2022-06-02T12:30:00+01:00\"\nnote = \"b\""
+        );
+
+        let dates = header.changelog().iter().map(|entry| entry.date().to_rfc3339()).collect::<Vec<_>>();
+        assert_eq!(dates, ["2022-06-01T00:00:00+00:00", "2022-06-02T11:30:00+00:00"]);
+
+        assert!(toml_header("\n[[changelog]]\ndate = \"June 2022\"\nnote = \"a\"").parse::<Header>().is_err());
+    }
+}
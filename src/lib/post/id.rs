@@ -6,21 +6,34 @@ use libshire::strings::ShString22;
 pub struct Id(ShString22);
 
 impl Id {
+    /// Returns the ID of the post file with the given name. Only the extension is checked, so
+    /// markdown files which are not posts also have an ID; use `PostSource::post_id` to check the
+    /// contents of the file as well.
     #[inline]
     #[must_use]
     pub fn from_file_name(file_name: &str) -> Option<Self> {
+        // Try the TOML extension first, since it ends with the YAML extension.
+        let prefix = file_name
+            .strip_suffix(super::POST_FILE_EXTENSION)
+            .or_else(|| file_name.strip_suffix(super::YAML_POST_FILE_EXTENSION))?;
+
+        Self::new(prefix)
+    }
+
+    /// Creates an ID from a string, which must be usable as the name of a post file without its
+    /// extension.
+    #[inline]
+    #[must_use]
+    pub fn new(id: &str) -> Option<Self> {
         fn is_invalid_char(c: char) -> bool {
             c == '/' || c == '\\' || c == '.'
         }
 
-        let prefix = file_name
-            .strip_suffix(super::POST_FILE_EXTENSION)?;
-
-        if prefix.contains(is_invalid_char) {
+        if id.is_empty() || id.contains(is_invalid_char) {
             return None;
         }
 
-        Some(Self(ShString22::new_from_str(prefix)))
+        Some(Self(ShString22::new_from_str(id)))
     }
 
     #[inline]
@@ -0,0 +1,180 @@
+use std::{collections::HashMap, path::{Component, Path, PathBuf}};
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, PercentEncode, NON_ALPHANUMERIC};
+
+use super::id::Id;
+
+/// The set of characters to percent-encode when inserting an arbitrary string into a URL as a
+/// single path segment. This leaves the RFC 3986 unreserved characters unencoded.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Percent-encodes a string so that it can be used as a single segment of a URL path.
+pub fn path_segment(segment: &str) -> PercentEncode<'_> {
+    utf8_percent_encode(segment, PATH_SEGMENT)
+}
+
+/// Returns the URL path of the post with the given ID.
+pub fn article_path(id: &str) -> String {
+    format!("/articles/{}", path_segment(id))
+}
+
+/// The posts which can be linked to with wiki links such as `[[post-id]]`, along with their
+/// titles, and the directories which links to post media and static files are checked against.
+#[derive(Clone, Default, Debug)]
+pub struct LinkTargets {
+    titles: HashMap<Id, String>,
+    aliases: HashMap<String, Id>,
+    media_dir: Option<PathBuf>,
+    static_dir: Option<PathBuf>,
+}
+
+impl LinkTargets {
+    /// Creates an empty set of targets. Links to post media and static files are not checked
+    /// unless the directories they are served from are given.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks links and images under `/article_media/` against the given directory.
+    #[must_use]
+    pub fn with_media_dir(self, media_dir: PathBuf) -> Self {
+        Self {
+            media_dir: Some(media_dir),
+            ..self
+        }
+    }
+
+    /// Checks links and images under `/static/` against the given directory.
+    #[must_use]
+    pub fn with_static_dir(self, static_dir: PathBuf) -> Self {
+        Self {
+            static_dir: Some(static_dir),
+            ..self
+        }
+    }
+
+    /// Adds a post which can be linked to. Links to any of the post's aliases are resolved to the
+    /// post.
+    pub fn insert<'a, I>(&mut self, id: Id, title: &str, aliases: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        for alias in aliases {
+            self.aliases.insert(alias.to_owned(), id.clone());
+        }
+
+        self.titles.insert(id, title.to_owned());
+    }
+
+    /// Returns the ID and title of the post with the given ID or alias. As when serving posts, a
+    /// post's ID takes precedence over another post's alias.
+    #[must_use]
+    pub fn resolve(&self, id: &str) -> Option<(&Id, &str)> {
+        self.titles
+            .get_key_value(id)
+            .or_else(|| {
+                let id = self.aliases.get(id)?;
+                self.titles.get_key_value(id.as_ref())
+            })
+            .map(|(id, title)| (id, title.as_str()))
+    }
+
+    /// Returns the path of the file which a link to post media or a static file refers to, if
+    /// the directory it is served from is known.
+    pub(super) fn file_path(&self, target: &LocalTarget) -> Option<PathBuf> {
+        match target {
+            LocalTarget::Post(_) | LocalTarget::OutsideDir(_) => None,
+            LocalTarget::Media(path) => self.media_dir.as_ref().map(|dir| dir.join(path)),
+            LocalTarget::Static(path) => self.static_dir.as_ref().map(|dir| dir.join(path)),
+        }
+    }
+}
+
+/// A post or file on this site which a link or image in a post's markdown refers to.
+#[derive(Clone, Debug)]
+pub(super) enum LocalTarget {
+    Post(String),
+    Media(String),
+    Static(String),
+    /// A path under `/article_media/` or `/static/` which could refer to a file outside of the
+    /// directory it is served from, such as `/static/..%2Fsecret`.
+    OutsideDir(String),
+}
+
+impl LocalTarget {
+    /// Parses the URL of a link or image which refers to a post, post media or a static file.
+    /// Returns `None` for any other URL, including links to other sites and relative links.
+    pub(super) fn from_url(url: &str) -> Option<Self> {
+        // Protocol-relative URLs such as `//example.com/static/x.png` point at other sites.
+        if !url.starts_with('/') || url.starts_with("//") {
+            return None;
+        }
+
+        let path = url
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default();
+
+        if let Some(id) = path.strip_prefix("/articles/") {
+            // `/articles/` on its own is the list of posts.
+            let id = id.trim_end_matches('/');
+            return if id.is_empty() { None } else { Some(Self::Post(decode(id))) };
+        }
+
+        if let Some(path) = path.strip_prefix("/article_media/") {
+            return Some(Self::file(path, Self::Media));
+        }
+
+        path.strip_prefix("/static/")
+            .map(|path| Self::file(path, Self::Static))
+    }
+
+    /// Decodes the path of a file relative to the directory it is served from. The path is
+    /// checked after decoding, since `..` components or a leading `/` could be percent-encoded.
+    fn file(path: &str, target: fn(String) -> Self) -> Self {
+        let path = decode(path);
+
+        let inside_dir = Path::new(&path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+        if inside_dir {
+            target(path)
+        } else {
+            Self::OutsideDir(path)
+        }
+    }
+}
+
+fn decode(path: &str) -> String {
+    percent_decode_str(path).decode_utf8_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LocalTarget;
+
+    #[test]
+    fn file_paths_outside_dir() {
+        assert!(matches!(
+            LocalTarget::from_url("/static/images/a%20b.png"),
+            Some(LocalTarget::Static(path)) if path == "images/a b.png"
+        ));
+        assert!(matches!(
+            LocalTarget::from_url("/static/..%2F..%2Fetc%2Fpasswd"),
+            Some(LocalTarget::OutsideDir(path)) if path == "../../etc/passwd"
+        ));
+        assert!(matches!(
+            LocalTarget::from_url("/static/images/../../secret"),
+            Some(LocalTarget::OutsideDir(_))
+        ));
+        assert!(matches!(
+            LocalTarget::from_url("/article_media/%2Fetc%2Fhostname"),
+            Some(LocalTarget::OutsideDir(path)) if path == "/etc/hostname"
+        ));
+    }
+}
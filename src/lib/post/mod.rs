@@ -1,16 +1,23 @@
+mod diagnostic;
 mod error;
 mod id;
 mod header;
+mod link;
 mod render;
 mod rendered_post;
 mod source;
+mod toc;
 
+pub use diagnostic::{Diagnostic, Report, Severity};
 pub use error::Error;
-pub use header::Header;
+pub use header::{Asset, AssetRoot, ChangelogEntry, Cover, Header, Layout, Robots, Series};
 pub use id::Id;
+pub use link::{article_path, path_segment, LinkTargets};
 pub use rendered_post::RenderedPost;
-pub use source::PostSource;
+pub use source::{HeaderFormat, PostSource};
+pub use toc::TocEntry;
 
 const POST_FILE_EXTENSION: &str = ".toml.md";
+const YAML_POST_FILE_EXTENSION: &str = ".md";
 
 pub type Post = RenderedPost;
@@ -1,59 +1,1210 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    ops::Range,
+};
+
 use maud::{html, Markup, PreEscaped};
 use pulldown_cmark::{
     CodeBlockKind,
     CowStr,
     Event,
+    HeadingLevel,
+    LinkType,
     Options,
     Parser,
     Tag,
+    escape::escape_html,
     html::push_html,
 };
 
-use crate::codeblock::CodeBlockRenderer;
+use crate::{
+    codeblock::CodeBlockRenderer,
+    math::{self, MathMode},
+    shortcode::{Invocation, LinkKind, Shortcode, ShortcodeError, ShortcodeRegistry},
+};
 
-pub(super) fn render_markdown(code_renderer: &CodeBlockRenderer, markdown: &str) -> Markup {
+use super::{
+    diagnostic::Diagnostic,
+    id::Id,
+    link::{article_path, LinkTargets, LocalTarget},
+    toc::{build_outline, Heading, TocEntry},
+};
+
+/// The maximum number of characters in a summary automatically extracted from a post's markdown.
+const SUMMARY_MAX_CHARS: usize = 300;
+
+pub(super) struct RenderedMarkdown {
+    pub(super) html: Markup,
+    pub(super) summary: Option<String>,
+    pub(super) toc: Vec<TocEntry>,
+    pub(super) word_count: usize,
+    pub(super) code_word_count: usize,
+    /// The IDs of the posts linked to, including those which do not exist.
+    pub(super) linked_posts: Vec<Id>,
+    /// Problems found while rendering, with line numbers relative to the start of the markdown.
+    pub(super) diagnostics: Vec<Diagnostic>,
+}
+
+pub(super) fn render_markdown(
+    code_renderer: &CodeBlockRenderer,
+    shortcodes: &ShortcodeRegistry,
+    link_targets: &LinkTargets,
+    markdown: &str
+) -> RenderedMarkdown
+{
     const PARSER_OPTIONS: Options = Options::ENABLE_TABLES
         .union(Options::ENABLE_FOOTNOTES)
-        .union(Options::ENABLE_STRIKETHROUGH);
+        .union(Options::ENABLE_STRIKETHROUGH)
+        .union(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    // Find the custom heading IDs given with the `{#id}` syntax before rendering, so that we can
+    // avoid generating the same ID for an earlier heading. Custom IDs are used as they are, so
+    // two headings with the same custom ID are reported rather than given different IDs.
+    let mut custom_heading_ids = HashSet::<String>::new();
+    let mut heading_id_warnings = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, PARSER_OPTIONS).into_offset_iter() {
+        if let Event::Start(Tag::Heading(_, Some(id), _)) = event {
+            if id.is_empty() || custom_heading_ids.insert(id.to_owned()) {
+                continue;
+            }
+
+            let warning = Diagnostic::warning(format!("duplicate heading ID `{}`", id));
+            let pos = markdown[range.clone()]
+                .rfind(&format!("#{}", id))
+                .map(|pos| range.start + pos);
+
+            heading_id_warnings.push(match pos {
+                Some(pos) => {
+                    let (line, column) = line_column(markdown, pos);
+                    warning.at(markdown, line, column, id.chars().count() + 1)
+                },
+                None => warning,
+            });
+        }
+    }
+
+    // Find the math before rendering shortcodes and wiki links, since TeX such as `\frac{{a}}{b}`
+    // or `[[0, 1]]` would otherwise be mistaken for them.
+    let math_ranges = MathParser::find_math_ranges(Parser::new_ext(markdown, PARSER_OPTIONS).into_offset_iter(), markdown);
 
     let mut parser = {
-        let parser = Parser::new_ext(markdown, PARSER_OPTIONS);
-        PostMdParser::new(parser, code_renderer)
+        let parser = Parser::new_ext(markdown, PARSER_OPTIONS).into_offset_iter();
+        let parser = ExtensionParser::new(parser, markdown, shortcodes, link_targets, math_ranges);
+        PostMdParser::new(MathParser::new(parser, markdown), code_renderer, custom_heading_ids)
     };
 
     let mut html_buf = String::new();
     push_html(&mut html_buf, parser.by_ref());
 
-    PreEscaped(html_buf)
+    let summary = parser.summary();
+
+    let mut linked_posts = parser.iter.iter.linked_posts;
+    let mut diagnostics = parser.iter.iter.diagnostics;
+    diagnostics.extend(parser.iter.warnings);
+    diagnostics.extend(heading_id_warnings);
+
+    // Check that the posts and files on this site which links and images refer to exist. Links to
+    // posts are recorded like wiki links, so that the post is rendered again and checked again if
+    // the posts it links to are added or removed.
+    let mut search_from = 0;
+    for (kind, url) in &parser.links {
+        if LocalTarget::from_url(url).is_none() {
+            continue;
+        }
+
+        // The links are in the order they appear in the source, so each URL is looked for after
+        // the previous one, falling back to its first occurrence for reference-style links
+        // whose definitions come first.
+        let pos = markdown[search_from..]
+            .find(url.as_str())
+            .map(|pos| search_from + pos)
+            .or_else(|| markdown.find(url.as_str()));
+
+        if let Some(pos) = pos {
+            search_from = pos + url.len();
+        }
+
+        if let Some(diagnostic) = check_link(link_targets, *kind, url, &mut linked_posts) {
+            let span = pos.map(|pos| (pos, url.chars().count()));
+            diagnostics.push(locate(diagnostic, markdown, span));
+        }
+    }
+
+    // The URLs used by shortcodes are reported along with the shortcode's position. A URL which is
+    // not written in the shortcode, such as that of a post card, is reported at the shortcode.
+    for (kind, url, range) in &parser.iter.iter.shortcode_links {
+        if let Some(diagnostic) = check_link(link_targets, *kind, url, &mut linked_posts) {
+            let source = &markdown[range.clone()];
+            let span = match source.find(url.as_str()) {
+                Some(pos) => (range.start + pos, url.chars().count()),
+                None => (range.start, source.lines().next().unwrap_or_default().chars().count()),
+            };
+            diagnostics.push(locate(diagnostic, markdown, Some(span)));
+        }
+    }
+
+    linked_posts.sort();
+    linked_posts.dedup();
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line(), diagnostic.column()));
+
+    RenderedMarkdown {
+        html: PreEscaped(html_buf),
+        summary,
+        word_count: parser.word_count,
+        code_word_count: parser.code_word_count,
+        toc: build_outline(parser.headings),
+        linked_posts,
+        diagnostics,
+    }
+}
+
+/// Checks that the post or file on this site which a URL refers to exists, returning an error if
+/// it does not, the same as for wiki links to posts which do not exist. The IDs of posts linked to
+/// are added to `linked_posts`.
+fn check_link(link_targets: &LinkTargets, kind: LinkKind, url: &str, linked_posts: &mut Vec<Id>) -> Option<Diagnostic> {
+    let target = LocalTarget::from_url(url)?;
+
+    let broken = |problem: String| format!("broken {}: {}", kind.name(), problem);
+
+    match &target {
+        LocalTarget::Post(id) => match link_targets.resolve(id) {
+            Some((id, _)) => {
+                linked_posts.push(id.clone());
+                None
+            },
+            None => {
+                linked_posts.extend(Id::new(id));
+                Some(Diagnostic::error(broken(format!("there is no post with the ID `{}`", id))))
+            },
+        },
+        LocalTarget::OutsideDir(path) => Some(Diagnostic::error(broken(format!(
+            "`{}` is outside the directory files are served from",
+            path
+        )))),
+        _ => link_targets
+            .file_path(&target)
+            .filter(|path| !path.is_file())
+            .map(|path| Diagnostic::error(broken(format!("there is no file at `{}`", path.to_string_lossy())))),
+    }
+}
+
+/// Places a diagnostic at the given position and length in the markdown, if it is known.
+fn locate(diagnostic: Diagnostic, markdown: &str, span: Option<(usize, usize)>) -> Diagnostic {
+    match span {
+        Some((pos, len)) => {
+            let (line, column) = line_column(markdown, pos);
+            diagnostic.at(markdown, line, column, len)
+        },
+        None => diagnostic,
+    }
+}
+
+/// Iterator struct which wraps an offset iterator over markdown events in order to render
+/// shortcodes, which are written as `{{ name key="value" }}`, and wiki links, which are written as
+/// `[[post-id]]` or `[[post-id|text]]`. Neither is looked for inside math, and the opening `{{` of
+/// a shortcode must be followed by whitespace, so that text such as `{{x}}` is left alone. A
+/// paragraph containing only a shortcode is replaced by the shortcode, since shortcodes may render
+/// block elements.
+struct ExtensionParser<'e, 's, I> {
+    iter: I,
+    markdown: &'e str,
+    shortcodes: &'s ShortcodeRegistry,
+    link_targets: &'s LinkTargets,
+    /// Events which have been read ahead to find the end of a run of inline events.
+    input: VecDeque<(Event<'e>, Range<usize>)>,
+    output: VecDeque<(Event<'e>, Range<usize>)>,
+    in_code_block: bool,
+    /// The shortcodes wrapping some content which are currently open, with their names and
+    /// positions in the source.
+    open: Vec<(String, &'s dyn Shortcode, usize)>,
+    /// The ranges of the source containing math, in which shortcodes and wiki links are not looked
+    /// for, since TeX uses braces and brackets too.
+    math_ranges: Vec<Range<usize>>,
+    /// The IDs of the posts linked to by wiki links, including those which do not exist.
+    linked_posts: Vec<Id>,
+    /// The URLs used by the shortcodes which were rendered, with the shortcodes' ranges in the
+    /// source.
+    shortcode_links: Vec<(LinkKind, String, Range<usize>)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// The kinds of syntax handled by `ExtensionParser`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Extension {
+    Shortcode,
+    WikiLink,
+}
+
+impl<'e, 's, I> ExtensionParser<'e, 's, I> where I: Iterator<Item = (Event<'e>, Range<usize>)> {
+    fn new(
+        iter: I,
+        markdown: &'e str,
+        shortcodes: &'s ShortcodeRegistry,
+        link_targets: &'s LinkTargets,
+        math_ranges: Vec<Range<usize>>
+    ) -> Self
+    {
+        Self {
+            iter,
+            markdown,
+            shortcodes,
+            link_targets,
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+            in_code_block: false,
+            open: Vec::new(),
+            math_ranges,
+            linked_posts: Vec::new(),
+            shortcode_links: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn next_input(&mut self) -> Option<(Event<'e>, Range<usize>)> {
+        self.input.pop_front().or_else(|| self.iter.next())
+    }
+
+    /// Reads events until the end of the current run of inline events, such as the content of a
+    /// paragraph.
+    fn read_inline_run(&mut self, run: &mut Vec<(Event<'e>, Range<usize>)>) {
+        while let Some((event, range)) = self.next_input() {
+            if !is_inline(&event) {
+                self.input.push_front((event, range));
+                break;
+            }
+            run.push((event, range));
+        }
+    }
+
+    /// Renders the shortcodes and wiki links in a paragraph, adding the results to `output`.
+    fn render_paragraph(&mut self, start: (Event<'e>, Range<usize>)) {
+        let mut run = Vec::new();
+        self.read_inline_run(&mut run);
+
+        let (run, rendered) = self.render_inline_run(run);
+
+        let only_shortcode = rendered == 1 && run
+            .iter()
+            .filter(|(event, _)| match event {
+                Event::Text(text) => !text.trim().is_empty(),
+                Event::SoftBreak => false,
+                _ => true,
+            })
+            .count() == 1;
+
+        let end_is_paragraph = matches!(self.input.front(), Some((Event::End(Tag::Paragraph), _)));
+
+        if only_shortcode && end_is_paragraph {
+            self.input.pop_front();
+            self.output.extend(run.into_iter().filter(|(event, _)| matches!(event, Event::Html(_))));
+        } else {
+            self.output.push_back(start);
+            self.output.extend(run);
+        }
+    }
+
+    /// Renders the shortcodes and wiki links in a run of inline events. Returns the rendered events
+    /// and the number of shortcodes which were rendered successfully.
+    fn render_inline_run(&mut self, run: Vec<(Event<'e>, Range<usize>)>) -> (Vec<(Event<'e>, Range<usize>)>, usize) {
+        let run_end = run.iter().map(|(_, range)| range.end).max().unwrap_or(0);
+        let mut queue = VecDeque::from(run);
+        let mut rendered = Vec::new();
+        let mut num_rendered = 0;
+
+        while let Some((event, range)) = queue.pop_front() {
+            // Extensions are only looked for in text whose source is the same as its text, which
+            // rules out text such as HTML entities.
+            let is_source_text = matches!(&event, Event::Text(text) if **text == self.markdown[range.clone()]);
+
+            let (open, extension) = match self.find_open(range.clone()).filter(|_| is_source_text) {
+                Some(open) => open,
+                None => {
+                    rendered.push((event, range));
+                    continue;
+                },
+            };
+
+            let close = match extension {
+                Extension::Shortcode => self.find_shortcode_close(open + 2, run_end),
+                Extension::WikiLink => self.find_wiki_link_close(open + 2, run_end),
+            };
+
+            let end = close.map(|close| close + 2);
+
+            // Extensions may span several events, since markdown treats some characters such as
+            // square brackets and asterisks specially. They are read from the source, so these
+            // events are dropped, as long as they do not affect anything outside the extension.
+            let spanned = end.map(|end| queue.iter().take_while(|(_, range)| range.start < end).count());
+            let valid = match spanned {
+                Some(spanned) => {
+                    let mut depth = 0usize;
+                    queue.iter().take(spanned).all(|(event, _)| match event {
+                        Event::Text(_) | Event::SoftBreak => true,
+                        Event::Start(tag) if is_format_tag(tag) => {
+                            depth += 1;
+                            true
+                        },
+                        Event::End(tag) if is_format_tag(tag) => match depth.checked_sub(1) {
+                            Some(new_depth) => {
+                                depth = new_depth;
+                                true
+                            },
+                            None => false,
+                        },
+                        _ => false,
+                    }) && depth == 0
+                },
+                None => false,
+            };
+
+            let (end, spanned) = match (end, spanned) {
+                (Some(end), Some(spanned)) if valid => (end, spanned),
+                _ => {
+                    // A shortcode containing markdown such as inline HTML would otherwise be left
+                    // as text without any explanation.
+                    if let (Extension::Shortcode, Some(end)) = (extension, end) {
+                        let len = self.markdown[open..end].lines().next().unwrap_or_default().chars().count();
+                        self.warn(open, len, "invalid shortcode: its arguments cannot contain markdown such as \
+                            inline HTML, code or links; escape the characters with backslashes, like `\\<b>`".to_owned());
+                    }
+
+                    // Keep looking in the rest of the text.
+                    push_text_event(&mut rendered, self.markdown, range.start..(open + 2).min(range.end));
+                    if open + 2 < range.end {
+                        queue.push_front((Event::Text(CowStr::Borrowed(&self.markdown[open + 2..range.end])), open + 2..range.end));
+                    }
+                    continue;
+                },
+            };
+
+            let last_end = queue
+                .drain(..spanned)
+                .next_back()
+                .map_or(range.end, |(_, range)| range.end);
+
+            push_text_event(&mut rendered, self.markdown, range.start..open);
+
+            match extension {
+                Extension::Shortcode => {
+                    let (html, success) = self.render_shortcode(open..end);
+                    rendered.push((Event::Html(CowStr::Boxed(html.into_string().into_boxed_str())), open..end));
+                    if success {
+                        num_rendered += 1;
+                    }
+                },
+                Extension::WikiLink => rendered.extend(self.render_wiki_link(open..end)),
+            }
+
+            if end < last_end {
+                queue.push_front((Event::Text(CowStr::Borrowed(&self.markdown[end..last_end])), end..last_end));
+            }
+        }
+
+        (rendered, num_rendered)
+    }
+
+    /// Finds the first `{{` followed by whitespace, or the first `[[`, in the given range of the
+    /// source which is not inside some math.
+    fn find_open(&self, range: Range<usize>) -> Option<(usize, Extension)> {
+        let text = &self.markdown[range.clone()];
+
+        let shortcode = text
+            .match_indices("{{")
+            .map(|(i, _)| range.start + i)
+            .find(|&open| {
+                !is_escaped(self.markdown, open)
+                    && !self.in_math(open)
+                    && matches!(self.markdown[open + 2..].chars().next(), Some(c) if c.is_whitespace())
+            })
+            .map(|open| (open, Extension::Shortcode));
+
+        // Markdown splits text at square brackets, so the second `[` of a wiki link is usually at the
+        // start of the next event.
+        let wiki_link = self.markdown
+            .get(range.start..range.end + 1)
+            .unwrap_or(text)
+            .match_indices("[[")
+            .map(|(i, _)| range.start + i)
+            .find(|&open| !is_escaped(self.markdown, open) && !self.in_math(open))
+            .map(|open| (open, Extension::WikiLink));
+
+        match (shortcode, wiki_link) {
+            (Some(shortcode), Some(wiki_link)) => Some(if shortcode.0 < wiki_link.0 { shortcode } else { wiki_link }),
+            (shortcode, wiki_link) => shortcode.or(wiki_link),
+        }
+    }
+
+    /// Returns whether the given position in the source is inside some math.
+    fn in_math(&self, pos: usize) -> bool {
+        self.math_ranges.iter().any(|range| range.contains(&pos))
+    }
+
+    /// Finds the `}}` which ends a shortcode, ignoring any inside quoted arguments.
+    fn find_shortcode_close(&self, start: usize, end: usize) -> Option<usize> {
+        let mut in_quote = false;
+        let mut escaped = false;
+        let mut prev_brace = false;
+
+        for (i, c) in self.markdown[start..end].char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_quote => escaped = true,
+                '"' => in_quote = !in_quote,
+                '}' if !in_quote && prev_brace => return Some(start + i - 1),
+                _ => (),
+            }
+            prev_brace = c == '}' && !in_quote;
+        }
+
+        None
+    }
+
+    /// Finds the `]]` which ends a wiki link. Wiki links must be on a single line, and cannot be
+    /// empty or contain any other square brackets.
+    fn find_wiki_link_close(&self, start: usize, end: usize) -> Option<usize> {
+        let text = &self.markdown[start..end];
+        let len = text.find(['[', ']', '\n']).unwrap_or(text.len());
+
+        if text[len..].starts_with("]]") && !text[..len].split('|').next().unwrap_or_default().trim().is_empty() {
+            Some(start + len)
+        } else {
+            None
+        }
+    }
+
+    /// Renders the shortcode at the given range of the source, which includes the braces. Returns
+    /// the rendered HTML and whether it was successful. If it was not, a warning is recorded and
+    /// the shortcode is shown as it was written instead.
+    fn render_shortcode(&mut self, range: Range<usize>) -> (Markup, bool) {
+        let source = &self.markdown[range.clone()];
+
+        let result = Invocation::parse(&source[2..source.len() - 2])
+            .and_then(|invocation| self.render_invocation(&invocation, range.clone()));
+
+        match result {
+            Ok(markup) => (markup, true),
+            Err(err) => {
+                let len = source.lines().next().unwrap_or_default().chars().count();
+                self.warn(range.start, len, format!("invalid shortcode: {}", err));
+                (html! { span .shortcode_error title=(err.message()) { (source) } }, false)
+            },
+        }
+    }
+
+    fn render_invocation(&mut self, invocation: &Invocation, range: Range<usize>) -> Result<Markup, ShortcodeError> {
+        let name = invocation.name();
+
+        if invocation.closing() {
+            return match self.open.last() {
+                Some((open_name, ..)) if open_name == name => {
+                    let (_, shortcode, _) = self.open.pop().unwrap();
+                    Ok(shortcode.render_end().unwrap_or_default())
+                },
+                _ => Err(ShortcodeError::new(format!("`{{{{ /{} }}}}` does not close an open `{}` shortcode", name, name))),
+            };
+        }
+
+        let shortcode = self.shortcodes
+            .get(name)
+            .ok_or_else(|| ShortcodeError::new(format!("unknown shortcode `{}`", name)))?;
+
+        let markup = shortcode.render(invocation.args(), self.link_targets)?;
+
+        for (kind, url) in shortcode.links(invocation.args()) {
+            self.shortcode_links.push((kind, url, range.clone()));
+        }
+
+        if shortcode.render_end().is_some() {
+            self.open.push((name.to_owned(), shortcode, range.start));
+        }
+
+        Ok(markup)
+    }
+
+    /// Closes the shortcodes which were left open at the end of the markdown, so that the HTML
+    /// they opened is closed too.
+    fn close_unclosed(&mut self) {
+        while let Some((name, shortcode, pos)) = self.open.pop() {
+            self.warn(pos, 2, format!("invalid shortcode: `{}` shortcode is never closed with `{{{{ /{} }}}}`", name, name));
+            let end = shortcode.render_end().unwrap_or_default();
+            let markdown_end = self.markdown.len();
+            self.output.push_back((Event::Html(CowStr::Boxed(end.into_string().into_boxed_str())), markdown_end..markdown_end));
+        }
+    }
+
+    /// Renders the wiki link at the given range of the source, which includes the brackets. Links
+    /// to posts which do not exist are recorded as errors, and shown as text.
+    fn render_wiki_link(&mut self, range: Range<usize>) -> Vec<(Event<'e>, Range<usize>)> {
+        let source = &self.markdown[range.start + 2..range.end - 2];
+
+        let (id, text) = match source.split_once('|') {
+            Some((id, text)) => (id.trim(), Some(text.trim()).filter(|text| !text.is_empty())),
+            None => (source.trim(), None),
+        };
+
+        match self.link_targets.resolve(id) {
+            Some((target, title)) => {
+                self.linked_posts.push(target.clone());
+
+                let tag = Tag::Link(
+                    LinkType::Inline,
+                    CowStr::Boxed(article_path(target).into_boxed_str()),
+                    CowStr::Borrowed("")
+                );
+
+                let text = text.unwrap_or(title).to_owned();
+
+                vec![
+                    (Event::Start(tag.clone()), range.clone()),
+                    (Event::Text(CowStr::Boxed(text.into_boxed_str())), range.clone()),
+                    (Event::End(tag), range),
+                ]
+            },
+
+            None => {
+                self.linked_posts.extend(Id::new(id));
+
+                let len = self.markdown[range.clone()].chars().count();
+                self.error(range.start, len, format!("broken wiki link: there is no post with the ID `{}`", id));
+
+                let html = html! {
+                    span .broken_link title={ "No post with the ID \"" (id) "\"" } { (text.unwrap_or(id)) }
+                };
+
+                vec![(Event::Html(CowStr::Boxed(html.into_string().into_boxed_str())), range)]
+            },
+        }
+    }
+
+    fn warn(&mut self, pos: usize, len: usize, message: String) {
+        let (line, column) = line_column(self.markdown, pos);
+        self.diagnostics.push(Diagnostic::warning(message).at(self.markdown, line, column, len));
+    }
+
+    fn error(&mut self, pos: usize, len: usize, message: String) {
+        let (line, column) = line_column(self.markdown, pos);
+        self.diagnostics.push(Diagnostic::error(message).at(self.markdown, line, column, len));
+    }
+}
+
+impl<'e, 's, I> Iterator for ExtensionParser<'e, 's, I> where I: Iterator<Item = (Event<'e>, Range<usize>)> {
+    type Item = (Event<'e>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.output.pop_front() {
+                return Some(item);
+            }
+
+            let (event, range) = match self.next_input() {
+                Some(item) => item,
+                None => {
+                    self.close_unclosed();
+                    return self.output.pop_front();
+                },
+            };
+
+            match &event {
+                Event::Start(Tag::CodeBlock(_)) => self.in_code_block = true,
+                Event::End(Tag::CodeBlock(_)) => self.in_code_block = false,
+                _ => (),
+            }
+
+            if self.in_code_block {
+                return Some((event, range));
+            }
+
+            match event {
+                Event::Start(Tag::Paragraph) => self.render_paragraph((event, range)),
+                // Inline events outside of a paragraph, such as in a heading or a tight list.
+                event if is_inline(&event) => {
+                    let mut run = vec![(event, range)];
+                    self.read_inline_run(&mut run);
+                    let (run, _) = self.render_inline_run(run);
+                    self.output.extend(run);
+                },
+                event => return Some((event, range)),
+            }
+        }
+    }
+}
+
+/// Returns whether an event can be part of a paragraph.
+fn is_inline(event: &Event) -> bool {
+    match event {
+        Event::Text(_)
+        | Event::Code(_)
+        | Event::Html(_)
+        | Event::SoftBreak
+        | Event::HardBreak
+        | Event::FootnoteReference(_)
+        | Event::TaskListMarker(_) => true,
+        Event::Start(tag) | Event::End(tag) => {
+            is_format_tag(tag) || matches!(tag, Tag::Link(..) | Tag::Image(..))
+        },
+        Event::Rule => false,
+    }
+}
+
+fn push_text_event<'e>(events: &mut Vec<(Event<'e>, Range<usize>)>, markdown: &'e str, range: Range<usize>) {
+    if !range.is_empty() {
+        events.push((Event::Text(CowStr::Borrowed(&markdown[range.clone()])), range));
+    }
+}
+
+/// Iterator struct which wraps an offset iterator over markdown events in order to render TeX
+/// math written between `$` delimiters for inline math, or `$$` delimiters for display math, to
+/// MathML. The math is taken from the markdown source rather than from the text events, since
+/// markdown syntax such as `*` or `\\` means something else in TeX, and the math may be split
+/// across several events.
+struct MathParser<'e, I> {
+    iter: I,
+    markdown: &'e str,
+    /// Events which have been read ahead while looking for the end of some math, but which turned
+    /// out not to be part of it.
+    input: VecDeque<(Event<'e>, Range<usize>)>,
+    output: VecDeque<Event<'e>>,
+    in_code_block: bool,
+    image_depth: usize,
+    /// For each emphasis, strong or strikethrough tag which is currently open, whether it was
+    /// opened inside some math. The end events of such tags are dropped, since the markup which
+    /// started them was really part of the math.
+    format_tags: Vec<bool>,
+    /// Whether to render the math found, rather than only recording where it is.
+    render: bool,
+    /// The ranges of the source containing math, including the delimiters.
+    math_ranges: Vec<Range<usize>>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl<'e, I> MathParser<'e, I> where I: Iterator<Item = (Event<'e>, Range<usize>)> {
+    fn new(iter: I, markdown: &'e str) -> Self {
+        Self {
+            iter,
+            markdown,
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+            in_code_block: false,
+            image_depth: 0,
+            format_tags: Vec::new(),
+            render: true,
+            math_ranges: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Finds the ranges of the source containing math without rendering it.
+    fn find_math_ranges(iter: I, markdown: &'e str) -> Vec<Range<usize>> {
+        let mut parser = Self {
+            render: false,
+            ..Self::new(iter, markdown)
+        };
+
+        parser.by_ref().for_each(drop);
+        parser.math_ranges
+    }
+
+    fn next_input(&mut self) -> Option<(Event<'e>, Range<usize>)> {
+        self.input.pop_front().or_else(|| self.iter.next())
+    }
+
+    /// Finds the math in a text event and renders it, adding the results to `output`. The text
+    /// event's text must be the same as its source.
+    fn render_text(&mut self, range: Range<usize>) {
+        let mut text_start = range.start;
+        let mut search_start = range.start;
+
+        while let Some((open, display)) = self.find_opener(search_start, range.end) {
+            let delim = if display { "$$" } else { "$" };
+            let tex_start = open + delim.len();
+
+            if let Some(close) = self.find_closer(tex_start, tex_start, range.end, display) {
+                self.push_text(text_start..open);
+                let tex = &self.markdown[tex_start..close];
+                self.push_math(tex, &[(0, tex_start)], open..(close + delim.len()), display);
+                text_start = close + delim.len();
+                search_start = text_start;
+            } else if self.render_multi_event_math(text_start..open, tex_start, display) {
+                return;
+            } else {
+                search_start = tex_start;
+            }
+        }
+
+        self.push_text(text_start..range.end);
+    }
+
+    /// Looks for the end of some math in the events following the current text event, which is
+    /// needed if the math contains a line break or characters which markdown treats as emphasis.
+    /// If it is found, the math is rendered and `true` is returned. Otherwise, the events read are
+    /// kept to be returned as normal and `false` is returned.
+    fn render_multi_event_math(&mut self, before: Range<usize>, tex_start: usize, display: bool) -> bool {
+        let mut read = Vec::new();
+        let mut close = None;
+
+        while let Some((event, range)) = self.next_input() {
+            match &event {
+                Event::Text(text) => {
+                    // Text which differs from its source, such as an HTML entity, cannot contain
+                    // the closing delimiter.
+                    if **text == self.markdown[range.clone()] {
+                        close = self.find_closer(tex_start, range.start, range.end, display);
+                    }
+                },
+                Event::SoftBreak | Event::HardBreak => (),
+                Event::Start(tag) | Event::End(tag) if is_format_tag(tag) => (),
+                _ => {
+                    read.push((event, range));
+                    break;
+                },
+            }
+
+            read.push((event, range));
+
+            if close.is_some() {
+                break;
+            }
+        }
+
+        let close = match close {
+            Some(close) => close,
+            None => {
+                for event in read.into_iter().rev() {
+                    self.input.push_front(event);
+                }
+                return false;
+            },
+        };
+
+        let (_, close_range) = read.pop().unwrap();
+
+        // The TeX is the source between the delimiters, except for anything between a line break
+        // and the next event, such as the `>` at the start of each line in a block quote.
+        let mut tex = String::new();
+        let mut segments = Vec::new();
+        let mut segment_start = Some(tex_start);
+        let mut min_start = tex_start;
+
+        let mut unclosed_ends = Vec::new();
+
+        for (event, range) in read {
+            match event {
+                Event::SoftBreak | Event::HardBreak => {
+                    if let Some(start) = segment_start.take() {
+                        segments.push((tex.len(), start));
+                        tex.push_str(&self.markdown[start..range.end]);
+                    }
+                    min_start = range.end;
+                },
+                event => {
+                    if segment_start.is_none() {
+                        segment_start = Some(range.start.max(min_start));
+                    }
+                    match event {
+                        Event::Start(_) => self.format_tags.push(true),
+                        Event::End(tag) => {
+                            // Tags which were opened before the math are closed after it.
+                            if let Some(false) = self.format_tags.pop() {
+                                unclosed_ends.push(Event::End(tag));
+                            }
+                        },
+                        _ => (),
+                    }
+                },
+            }
+        }
+
+        let start = segment_start.unwrap_or_else(|| close_range.start.max(min_start));
+        segments.push((tex.len(), start));
+        tex.push_str(&self.markdown[start..close]);
+
+        let delim_len = if display { 2 } else { 1 };
+        self.push_text(before);
+        self.push_math(&tex, &segments, (tex_start - delim_len)..(close + delim_len), display);
+        self.output.extend(unclosed_ends);
+
+        // Any text after the math may contain more math, so it is read again.
+        let rest_start = close + delim_len;
+        if rest_start < close_range.end {
+            let rest = &self.markdown[rest_start..close_range.end];
+            self.input.push_front((Event::Text(CowStr::Borrowed(rest)), rest_start..close_range.end));
+        }
+
+        true
+    }
+
+    /// Finds the next `$` or `$$` between the given positions in the source which could start some
+    /// math, returning its position and whether it starts display math. Inline math cannot start
+    /// with whitespace, so that text such as "between $5 and $10" is left alone.
+    fn find_opener(&self, start: usize, end: usize) -> Option<(usize, bool)> {
+        let bytes = self.markdown.as_bytes();
+        let mut i = start;
+
+        while i < end {
+            if bytes[i] == b'$' && !is_escaped(self.markdown, i) {
+                if i + 1 < end && bytes[i + 1] == b'$' {
+                    return Some((i, true));
+                }
+                // Markdown splits text at square brackets, so the math may continue in the next
+                // event.
+                let next = self.markdown[i + 1..].chars().next();
+                if matches!(next, Some(c) if !c.is_whitespace()) {
+                    return Some((i, false));
+                }
+            }
+            i += 1;
+        }
+
+        None
+    }
+
+    /// Finds the first `$` or `$$` between the given positions in the source which could end
+    /// math starting at `tex_start`. Inline math cannot end with whitespace or be followed
+    /// directly by a digit. Inline math which starts with a digit cannot contain whitespace, so
+    /// that prices such as "costs $5 and $10 here, x$ done" are left alone; math such as `$2^n$`
+    /// is still found, and math such as `${2 + 3}$` can be written with braces.
+    fn find_closer(&self, tex_start: usize, start: usize, end: usize, display: bool) -> Option<usize> {
+        let bytes = self.markdown.as_bytes();
+        let starts_with_digit = matches!(bytes.get(tex_start), Some(b) if b.is_ascii_digit());
+
+        (start..end).find(|&i| {
+            if bytes[i] != b'$' || is_escaped(self.markdown, i) {
+                return false;
+            }
+            if display {
+                return i + 1 < end && bytes[i + 1] == b'$';
+            }
+            let prev = self.markdown[..i].chars().next_back();
+            let next = self.markdown[i + 1..].chars().next();
+            i > tex_start
+                && !matches!(prev, Some(c) if c.is_whitespace())
+                && !matches!(next, Some(c) if c.is_ascii_digit())
+                && (!starts_with_digit || !self.markdown[tex_start..i].contains(char::is_whitespace))
+        })
+    }
+
+    fn push_text(&mut self, range: Range<usize>) {
+        if !range.is_empty() {
+            self.output.push_back(Event::Text(CowStr::Borrowed(&self.markdown[range])));
+        }
+    }
+
+    /// Renders some TeX to MathML and adds it to `output`. `segments` maps positions in the TeX to
+    /// positions in the source, giving the position in each where a piece of the TeX starts, and
+    /// `range` is the range of the source containing the math and its delimiters. If the TeX is
+    /// invalid, a warning is recorded and the TeX is shown as it was written instead.
+    fn push_math(&mut self, tex: &str, segments: &[(usize, usize)], range: Range<usize>, display: bool) {
+        self.math_ranges.push(range);
+
+        if !self.render {
+            return;
+        }
+
+        let mode = if display { MathMode::Display } else { MathMode::Inline };
+
+        let markup = match math::render(tex, mode) {
+            Ok(markup) => markup,
+            Err(err) => {
+                let span = err.span();
+                let pos = segments
+                    .iter()
+                    .rev()
+                    .find(|(tex_pos, _)| *tex_pos <= span.start)
+                    .map_or(0, |(tex_pos, source_pos)| source_pos + (span.start - tex_pos));
+
+                let (line, column) = line_column(self.markdown, pos);
+                let len = self.markdown
+                    .get(pos..pos + span.len())
+                    .map_or(1, |s| s.chars().count());
+
+                self.warnings.push(Diagnostic::warning(format!("invalid math: {}", err))
+                    .at(self.markdown, line, column, len));
+
+                let delim = if display { "$$" } else { "$" };
+                html! {
+                    span .math_error title=(err.message()) { (delim) (tex) (delim) }
+                }
+            },
+        };
+
+        self.output.push_back(Event::Html(CowStr::Boxed(markup.into_string().into_boxed_str())));
+    }
+}
+
+impl<'e, I> Iterator for MathParser<'e, I> where I: Iterator<Item = (Event<'e>, Range<usize>)> {
+    type Item = Event<'e>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.output.pop_front() {
+                return Some(event);
+            }
+
+            let (event, range) = self.next_input()?;
+
+            match &event {
+                Event::Start(Tag::CodeBlock(_)) => self.in_code_block = true,
+                Event::End(Tag::CodeBlock(_)) => self.in_code_block = false,
+                Event::Start(Tag::Image(..)) => self.image_depth += 1,
+                Event::End(Tag::Image(..)) => self.image_depth = self.image_depth.saturating_sub(1),
+                Event::Start(tag) if is_format_tag(tag) => self.format_tags.push(false),
+                Event::End(tag) if is_format_tag(tag) => {
+                    if let Some(true) = self.format_tags.pop() {
+                        continue;
+                    }
+                },
+                // Math is only looked for in text whose source is the same as its text, which
+                // rules out text such as HTML entities.
+                Event::Text(text) if !self.in_code_block
+                    && self.image_depth == 0
+                    && text.contains('$')
+                    && **text == self.markdown[range.clone()] =>
+                {
+                    self.render_text(range);
+                    continue;
+                },
+                _ => (),
+            }
+
+            return Some(event);
+        }
+    }
+}
+
+fn is_format_tag(tag: &Tag) -> bool {
+    matches!(tag, Tag::Emphasis | Tag::Strong | Tag::Strikethrough)
 }
 
-/// Iterator struct which wraps another event iterator in order to render code blocks, collect the links
-/// encountered and generate a summary of the text content.
-struct PostMdParser<'p, I> {
+/// Returns whether the character at the given position in some text is escaped by an odd number
+/// of backslashes.
+fn is_escaped(text: &str, pos: usize) -> bool {
+    let backslashes = text[..pos]
+        .bytes()
+        .rev()
+        .take_while(|b| *b == b'\\')
+        .count();
+
+    backslashes % 2 == 1
+}
+
+/// Returns the line and column of a position in some text, both of which start at 1.
+fn line_column(text: &str, pos: usize) -> (usize, usize) {
+    let before = &text[..pos];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// Iterator struct which wraps another event iterator in order to render code blocks, headings
+/// and callouts, and to collect information about the post as it goes: the links encountered, the
+/// headings for the table of contents, the word counts, a summary of the text content and
+/// diagnostics for any problems found.
+struct PostMdParser<'e, 'p, I> {
     iter: I,
+    /// Events which have been read ahead, for example to check whether a block quote is a
+    /// callout, and which have not been observed or rendered yet.
+    pending: VecDeque<Event<'e>>,
     code_renderer: &'p CodeBlockRenderer,
+    summary_buf: String,
+    summary_state: SummaryState,
+    image_depth: usize,
+    in_code_block: bool,
+    headings: Vec<Heading>,
+    heading_ids: HashSet<String>,
+    word_count: usize,
+    code_word_count: usize,
+    at_word_boundary: bool,
+    /// For each block quote which is currently open, whether it is being rendered as a callout.
+    block_quotes: Vec<bool>,
+    /// The URLs of the links and images in the post, in the order they appear.
+    links: Vec<(LinkKind, String)>,
+}
+
+/// The progress of collecting the summary, which is the text content of the first paragraph of
+/// the post which contains any text.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SummaryState {
+    NotStarted,
+    InParagraph,
+    Done,
 }
 
-impl<'p, I> PostMdParser<'p, I> {
-    fn new(iter: I, code_renderer: &'p CodeBlockRenderer) -> Self {
+impl<'e, 'p, I> PostMdParser<'e, 'p, I> {
+    fn new(iter: I, code_renderer: &'p CodeBlockRenderer, heading_ids: HashSet<String>) -> Self {
         Self {
             iter,
+            pending: VecDeque::new(),
             code_renderer,
+            summary_buf: String::new(),
+            summary_state: SummaryState::NotStarted,
+            image_depth: 0,
+            in_code_block: false,
+            headings: Vec::new(),
+            heading_ids,
+            word_count: 0,
+            code_word_count: 0,
+            at_word_boundary: true,
+            block_quotes: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    /// Updates the information collected about the post's content with the given event. This is
+    /// called for every event before it is rendered.
+    fn observe(&mut self, event: &Event) {
+        match event {
+            Event::Start(Tag::Image(..)) => self.image_depth += 1,
+            Event::End(Tag::Image(..)) => self.image_depth = self.image_depth.saturating_sub(1),
+            // Fenced code blocks are consumed in their entirety by `render_event`, so we will
+            // never see their end event. Indented code blocks are rendered as normal, so we need
+            // to keep track of them in order to avoid treating their contents as prose.
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Indented)) => self.in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => self.in_code_block = false,
+            _ => (),
+        }
+
+        self.collect_summary(event);
+        self.collect_link(event);
+        self.count_words(event);
+    }
+
+    fn collect_link(&mut self, event: &Event) {
+        match event {
+            // Email autolinks such as `<me@example.com>` have the address as their URL, without
+            // the `mailto:`.
+            Event::Start(Tag::Link(LinkType::Email, ..)) => (),
+            Event::Start(Tag::Link(_, url, _)) => self.links.push((LinkKind::Link, url.to_string())),
+            Event::Start(Tag::Image(_, url, _)) => self.links.push((LinkKind::Image, url.to_string())),
+            _ => (),
+        }
+    }
+
+    /// Generates an ID for a heading from its text which is not used by any other heading. If
+    /// several headings have the same slug, the first gets the plain slug and the others get a
+    /// numeric suffix after an underscore. Slugs never contain underscores, so the suffixed IDs
+    /// cannot collide with the slug of another heading, and the ID of a heading only depends on
+    /// the custom IDs and the headings with the same slug.
+    fn unique_heading_id(&mut self, title: &str) -> String {
+        let slug = slugify(title);
+
+        let mut id = slug.clone();
+        let mut n = 1;
+        while self.heading_ids.contains(&id) {
+            n += 1;
+            id = format!("{}_{}", slug, n);
+        }
+
+        self.heading_ids.insert(id.clone());
+        id
+    }
+
+    /// Counts the words of prose in the post, which excludes code blocks and image alt text. A
+    /// word may be split across several text events, for example if part of it is emphasised, so
+    /// we only count a new word when non-whitespace text follows whitespace or a block boundary.
+    /// Words in indented code blocks are counted separately; fenced code blocks are counted by
+    /// `render_event`.
+    fn count_words(&mut self, event: &Event) {
+        match event {
+            Event::Text(text) | Event::Code(text) => {
+                if self.in_code_block {
+                    self.code_word_count += text.split_whitespace().count();
+                    return;
+                }
+
+                if self.image_depth > 0 {
+                    return;
+                }
+
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        self.at_word_boundary = true;
+                    } else if self.at_word_boundary {
+                        self.word_count += 1;
+                        self.at_word_boundary = false;
+                    }
+                }
+            },
+
+            // Inline formatting does not separate words.
+            Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..))
+            | Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..)) => (),
+
+            _ => self.at_word_boundary = true,
+        }
+    }
+
+    /// Returns the summary collected so far with its whitespace normalised, truncated at a word
+    /// boundary to at most `SUMMARY_MAX_CHARS` characters.
+    fn summary(&self) -> Option<String> {
+        let mut words = self.summary_buf.split_whitespace();
+        let mut summary = words.next()?.to_owned();
+        let mut summary_chars = summary.chars().count();
+
+        for word in words {
+            let word_chars = word.chars().count();
+            if summary_chars + word_chars + 1 > SUMMARY_MAX_CHARS {
+                summary.push('…');
+                break;
+            }
+            summary.push(' ');
+            summary.push_str(word);
+            summary_chars += word_chars + 1;
+        }
+
+        Some(summary)
+    }
+
+    fn collect_summary(&mut self, event: &Event) {
+        match self.summary_state {
+            SummaryState::Done => (),
+
+            SummaryState::NotStarted => {
+                if let Event::Start(Tag::Paragraph) = event {
+                    self.summary_state = SummaryState::InParagraph;
+                }
+            },
+
+            SummaryState::InParagraph => match event {
+                // Paragraphs with no text, such as paragraphs containing only an image, are
+                // skipped and we wait for the next paragraph instead.
+                Event::End(Tag::Paragraph) => {
+                    self.summary_state = if self.summary_buf.trim().is_empty() {
+                        SummaryState::NotStarted
+                    } else {
+                        SummaryState::Done
+                    };
+                },
+                // The text inside an image is its alt text, which should not be part of the
+                // summary.
+                Event::Text(text) | Event::Code(text) if self.image_depth == 0 => {
+                    self.summary_buf.push_str(text);
+                },
+                Event::SoftBreak | Event::HardBreak => self.summary_buf.push(' '),
+                _ => (),
+            },
         }
     }
 }
 
-impl<'e, 'p, I> Iterator for PostMdParser<'p, I> where I: Iterator<Item = pulldown_cmark::Event<'e>> {
-    type Item = pulldown_cmark::Event<'e>;
+impl<'e, 'p, I> PostMdParser<'e, 'p, I> where I: Iterator<Item = pulldown_cmark::Event<'e>> {
+    fn next_event(&mut self) -> Option<Event<'e>> {
+        self.pending.pop_front().or_else(|| self.iter.next())
+    }
+
+    fn render_event(&mut self, event: Event<'e>) -> Event<'e> {
+        match event {
+            // Headings are rendered as a whole, because we need to know the heading's text to
+            // generate its ID before we can write the opening tag.
+            Event::Start(Tag::Heading(level, custom_id, classes)) => {
+                let heading = self.render_heading(level, custom_id, &classes);
+                Event::Html(CowStr::Boxed(heading.into_boxed_str()))
+            },
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|event| match event {
             // When we reach a code block, we want to collect the text content until the code block finishes
             // and have the `CodeBlockRenderer` render it
             Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
                 let mut code_buf = String::new();
 
-                for event in self.iter.by_ref() {
+                while let Some(event) = self.next_event() {
                     match event {
                         // The code block has finished, so break out of the loop
                         Event::End(Tag::CodeBlock(_)) => break,
@@ -65,10 +1216,19 @@ impl<'e, 'p, I> Iterator for PostMdParser<'p, I> where I: Iterator<Item = pulldo
                     }
                 }
 
+                self.code_word_count += code_buf.split_whitespace().count();
+
                 let highlighted = self.code_renderer.render(&lang, &code_buf);
                 Event::Html(CowStr::Boxed(highlighted.into_string().into_boxed_str()))
             },
 
+            Event::Start(Tag::BlockQuote) => self.render_block_quote_start(),
+
+            Event::End(Tag::BlockQuote) => match self.block_quotes.pop() {
+                Some(true) => Event::Html(CowStr::Borrowed("</aside>\n")),
+                _ => Event::End(Tag::BlockQuote),
+            },
+
             Event::Code(code) => {
                 Event::Html(CowStr::Boxed(html! {
                     code .inline_code { (code) }
@@ -76,6 +1236,483 @@ impl<'e, 'p, I> Iterator for PostMdParser<'p, I> where I: Iterator<Item = pulldo
             },
 
             event => event,
+        }
+    }
+
+    /// Renders the start of a block quote. Block quotes whose first line is a marker such as
+    /// `[!NOTE]` or `[!WARNING]` are rendered as callouts, using the same syntax as GitHub.
+    fn render_block_quote_start(&mut self) -> Event<'e> {
+        let mut read = Vec::new();
+        let callout = self.read_callout_marker(&mut read);
+
+        self.block_quotes.push(callout.is_some());
+
+        match callout {
+            Some(callout) => {
+                // The marker is not part of the content, but the rest of its paragraph is.
+                if !matches!(read.last(), Some(Event::End(Tag::Paragraph))) {
+                    self.pending.push_front(Event::Start(Tag::Paragraph));
+                }
+
+                Event::Html(CowStr::Boxed(format!(
+                    "\n<aside class=\"callout callout_{}\">\n\
+                     <p class=\"callout_title\"><span class=\"callout_icon\" aria-hidden=\"true\"></span>{}</p>\n",
+                    callout.class(),
+                    callout.title()
+                ).into_boxed_str()))
+            },
+
+            None => {
+                for event in read.into_iter().rev() {
+                    self.pending.push_front(event);
+                }
+                Event::Start(Tag::BlockQuote)
+            },
+        }
+    }
+
+    /// Reads the events at the start of a block quote to find a callout marker on a line of its
+    /// own. The events read are added to `read`, so that they can be rendered as normal if there
+    /// is no marker.
+    fn read_callout_marker(&mut self, read: &mut Vec<Event<'e>>) -> Option<Callout> {
+        // The longest valid marker is `[!IMPORTANT]`, so there is no need to read any further.
+        const MAX_MARKER_LEN: usize = 12;
+
+        let event = self.next_event()?;
+        let is_paragraph = matches!(event, Event::Start(Tag::Paragraph));
+        read.push(event);
+        if !is_paragraph {
+            return None;
+        }
+
+        let mut marker = String::new();
+
+        // The marker may be split across several text events, because markdown treats the square
+        // brackets specially.
+        loop {
+            let event = self.next_event()?;
+            let is_text = match &event {
+                Event::Text(text) if marker.len() <= MAX_MARKER_LEN => {
+                    marker.push_str(text);
+                    true
+                },
+                Event::SoftBreak | Event::HardBreak | Event::End(Tag::Paragraph) => false,
+                _ => {
+                    read.push(event);
+                    return None;
+                },
+            };
+            read.push(event);
+            if !is_text {
+                break;
+            }
+        }
+
+        Callout::from_marker(marker.trim())
+    }
+
+    fn render_heading(&mut self, level: HeadingLevel, custom_id: Option<&str>, classes: &[&str]) -> String {
+        let mut title = String::new();
+        let mut content = Vec::new();
+
+        // Collect the events inside the heading, rendering each of them as we would if they were
+        // not in a heading.
+        while let Some(event) = self.next_event() {
+            self.observe(&event);
+
+            match &event {
+                Event::End(Tag::Heading(..)) => break,
+                Event::Text(text) | Event::Code(text) if self.image_depth == 0 => {
+                    title.push_str(text);
+                },
+                _ => (),
+            }
+
+            content.push(self.render_event(event));
+        }
+
+        let title = title.trim().to_owned();
+
+        // An empty custom ID such as `{#}` is ignored, since it would not be a valid ID.
+        let id = match custom_id {
+            Some(custom_id) if !custom_id.is_empty() => custom_id.to_owned(),
+            _ => self.unique_heading_id(&title),
+        };
+
+        let mut buf = String::new();
+
+        // Writing to a `String` cannot fail, so the results are ignored.
+        buf.push_str(&format!("\n<{} id=\"", level));
+        let _ = escape_html(&mut buf, &id);
+        buf.push('"');
+        if !classes.is_empty() {
+            buf.push_str(" class=\"");
+            let _ = escape_html(&mut buf, &classes.join(" "));
+            buf.push('"');
+        }
+        buf.push('>');
+        push_html(&mut buf, content.into_iter());
+        buf.push_str(&html! {
+            " "
+            a .heading_anchor href={"#" (id)} aria-label={"Link to section: " (title)} { "#" }
+        }.into_string());
+        buf.push_str(&format!("</{}>\n", level));
+
+        self.headings.push(Heading {
+            level: level as u8,
+            id,
+            title,
+        });
+
+        buf
+    }
+}
+
+/// The kinds of callout which a block quote can be rendered as.
+#[derive(Clone, Copy)]
+enum Callout {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl Callout {
+    /// Parses a callout marker such as `[!NOTE]`. The kind of callout is case-insensitive.
+    fn from_marker(marker: &str) -> Option<Self> {
+        let kind = marker
+            .strip_prefix("[!")?
+            .strip_suffix(']')?
+            .to_ascii_lowercase();
+
+        match kind.as_str() {
+            "note" | "info" => Some(Self::Note),
+            "tip" | "hint" => Some(Self::Tip),
+            "important" => Some(Self::Important),
+            "warning" => Some(Self::Warning),
+            "caution" | "danger" => Some(Self::Caution),
+            _ => None,
+        }
+    }
+
+    /// Returns the suffix of the callout's class, which determines its colour and icon.
+    fn class(self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::Tip => "tip",
+            Self::Important => "important",
+            Self::Warning => "warning",
+            Self::Caution => "caution",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::Tip => "Tip",
+            Self::Important => "Important",
+            Self::Warning => "Warning",
+            Self::Caution => "Caution",
+        }
+    }
+}
+
+/// Converts a heading's text to a string suitable for use as an ID: letters and digits are
+/// lowercased, runs of whitespace, hyphens and underscores become a single hyphen, and all other
+/// characters are removed.
+fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    let mut pending_hyphen = false;
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if pending_hyphen && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_hyphen = false;
+            slug.extend(c.to_lowercase());
+        } else if c.is_whitespace() || c == '-' || c == '_' {
+            pending_hyphen = true;
+        }
+    }
+
+    if slug.is_empty() {
+        slug.push_str("section");
+    }
+
+    slug
+}
+
+impl<'e, 'p, I> Iterator for PostMdParser<'e, 'p, I> where I: Iterator<Item = pulldown_cmark::Event<'e>> {
+    type Item = pulldown_cmark::Event<'e>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().map(|event| {
+            self.observe(&event);
+            self.render_event(event)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::{codeblock::CodeBlockRenderer, post::Severity, shortcode::ShortcodeRegistry, test_util::TempDir};
+
+    use super::{render_markdown, Id, LinkTargets, RenderedMarkdown};
+
+    fn render(markdown: &str) -> RenderedMarkdown {
+        render_markdown(&CodeBlockRenderer::new(), &ShortcodeRegistry::new(), &LinkTargets::new(), markdown)
+    }
+
+    fn heading_ids(rendered: &RenderedMarkdown) -> Vec<&str> {
+        rendered.toc.iter().map(|entry| entry.id()).collect()
+    }
+
+    fn summary(markdown: &str) -> Option<String> {
+        render(markdown).summary
+    }
+
+    #[test]
+    fn summary_is_the_text_of_the_first_paragraph() {
+        assert_eq!(
+            summary("Some *emphasised* and **strong** text,\nwith [a link](https://example.com) and `code`.\n\nMore text.").as_deref(),
+            Some("Some emphasised and strong text, with a link and code.")
+        );
+        assert_eq!(summary("  Extra   spaces\nare  removed.  ").as_deref(), Some("Extra spaces are removed."));
+        assert_eq!(summary(""), None);
+    }
+
+    #[test]
+    fn summary_skips_leading_headings_and_images() {
+        assert_eq!(summary("# Heading\n\nThe first paragraph.").as_deref(), Some("The first paragraph."));
+        assert_eq!(summary("![Alt text](image.png)\n\nThe first paragraph.").as_deref(), Some("The first paragraph."));
+        assert_eq!(summary("![Alt text](image.png) A caption.").as_deref(), Some("A caption."));
+        assert_eq!(summary("```\ncode\n```\n\n> Quoted.").as_deref(), Some("Quoted."));
+        assert_eq!(summary("# Only a heading"), None);
+    }
+
+    #[test]
+    fn long_summary_is_truncated_at_a_word() {
+        let summary = summary(&"word ".repeat(100)).unwrap();
+        assert!(summary.ends_with("word…"));
+        assert!(summary.chars().count() <= super::SUMMARY_MAX_CHARS + 1);
+    }
+
+    #[test]
+    fn unknown_wiki_link_is_an_error() {
+        let rendered = render("See [[missing]].");
+        assert_eq!(rendered.diagnostics.len(), 1);
+        assert_eq!(rendered.diagnostics[0].severity(), Severity::Error);
+        assert_eq!(rendered.linked_posts, [Id::new("missing").unwrap()]);
+        assert!(rendered.html.into_string().contains("<span class=\"broken_link\""));
+    }
+
+    #[test]
+    fn broken_post_link_is_an_error() {
+        let rendered = render("See [this](/articles/missing) and [[missing]].");
+        assert_eq!(rendered.diagnostics.len(), 2);
+        assert!(rendered.diagnostics.iter().all(|diagnostic| diagnostic.severity() == Severity::Error));
+        assert_eq!(rendered.diagnostics[0].column(), Some(12));
+        assert_eq!(rendered.diagnostics[1].column(), Some(35));
+    }
+
+    #[test]
+    fn math_is_not_a_wiki_link() {
+        let rendered = render("An interval $[[0,1]]$ of numbers.");
+        let html = rendered.html.into_string();
+        assert!(rendered.diagnostics.is_empty());
+        assert!(html.contains("<math>"));
+        assert!(!html.contains("broken_link"));
+    }
+
+    #[test]
+    fn math_is_not_a_shortcode() {
+        let rendered = render("A fraction $\\frac{{ a}}{b}$ of numbers.");
+        let html = rendered.html.into_string();
+        assert!(rendered.diagnostics.is_empty());
+        assert!(html.contains("<mfrac>"));
+        assert!(!html.contains("shortcode_error"));
+    }
+
+    #[test]
+    fn shortcode_containing_html() {
+        let rendered = render("{{ figure src=\"/a.png\" alt=\"<b>x</b>\" }}\n");
+        assert_eq!(rendered.diagnostics.len(), 1);
+        assert!(rendered.diagnostics[0].message().starts_with("invalid shortcode: "));
+        assert_eq!(rendered.diagnostics[0].severity(), Severity::Warning);
+        assert_eq!(rendered.diagnostics[0].column(), Some(1));
+
+        let rendered = render("{{ figure src=\"/a.png\" alt=\"\\<b>x\\</b>\" }}\n");
+        assert!(rendered.diagnostics.is_empty());
+        assert!(rendered.html.into_string().contains("alt=\"&lt;b&gt;x&lt;/b&gt;\""));
+    }
+
+    #[test]
+    fn prices_are_not_math() {
+        let rendered = render("Costs $5 and $10 here, x$ done.");
+        let html = rendered.html.into_string();
+        assert!(rendered.diagnostics.is_empty());
+        assert!(html.contains("Costs $5 and $10 here, x$ done."));
+
+        let rendered = render("Powers $2^n$ and sums ${2 + 3}$, but $2 + 3$ is text.");
+        let html = rendered.html.into_string();
+        assert!(rendered.diagnostics.is_empty());
+        assert_eq!(html.matches("<math>").count(), 2);
+        assert!(html.contains("$2 + 3$ is text."));
+    }
+
+    fn callout_start(kind: &str, title: &str) -> String {
+        format!(
+            "\n<aside class=\"callout callout_{}\">\n\
+             <p class=\"callout_title\"><span class=\"callout_icon\" aria-hidden=\"true\"></span>{}</p>\n",
+            kind,
+            title
+        )
+    }
+
+    #[test]
+    fn callout_marker_on_own_line() {
+        let html = render("> [!NOTE]\n> Body *text*.\n").html.into_string();
+        assert_eq!(html, format!("{}<p>Body <em>text</em>.</p>\n</aside>\n", callout_start("note", "Note")));
+
+        let html = render("> [!warning]\n> Body\n> text.\n").html.into_string();
+        assert_eq!(html, format!("{}<p>Body\ntext.</p>\n</aside>\n", callout_start("warning", "Warning")));
+    }
+
+    #[test]
+    fn callout_marker_with_text_is_a_block_quote() {
+        let html = render("> [!NOTE] Body text.\n").html.into_string();
+        assert_eq!(html, "<blockquote>\n<p>[!NOTE] Body text.</p>\n</blockquote>\n");
+
+        let html = render("> [!UNKNOWN]\n> Body text.\n").html.into_string();
+        assert_eq!(html, "<blockquote>\n<p>[!UNKNOWN]\nBody text.</p>\n</blockquote>\n");
+    }
+
+    #[test]
+    fn callout_marker_only() {
+        let html = render("> [!TIP]\n").html.into_string();
+        assert_eq!(html, format!("{}</aside>\n", callout_start("tip", "Tip")));
+    }
+
+    #[test]
+    fn nested_callouts() {
+        let html = render("> [!IMPORTANT]\n> Outer.\n>\n> > [!CAUTION]\n> > Inner.\n").html.into_string();
+        assert_eq!(html, format!(
+            "{}<p>Outer.</p>\n{}<p>Inner.</p>\n</aside>\n</aside>\n",
+            callout_start("important", "Important"),
+            callout_start("caution", "Caution")
+        ));
+    }
+
+    #[test]
+    fn plain_block_quote_is_unchanged() {
+        let markdown = "> A *quote*\n> over two lines.\n>\n> > And a nested [quote](https://example.com).\n";
+
+        let mut expected = String::new();
+        pulldown_cmark::html::push_html(&mut expected, pulldown_cmark::Parser::new(markdown));
+
+        assert_eq!(render(markdown).html.into_string(), expected);
+    }
+
+    #[test]
+    fn heading_ids_without_text() {
+        let rendered = render("# !!!\n\n# ???\n\n# Title {#}\n");
+        assert_eq!(heading_ids(&rendered), ["section", "section_2", "title"]);
+    }
+
+    #[test]
+    fn heading_ids_do_not_depend_on_other_headings() {
+        let rendered = render("# Setup\n\n# Setup\n\n# Setup 2\n");
+        assert_eq!(heading_ids(&rendered), ["setup", "setup_2", "setup-2"]);
+
+        let rendered = render("# Setup 2\n\n# Setup\n\n# Setup\n");
+        assert_eq!(heading_ids(&rendered), ["setup-2", "setup", "setup_2"]);
+
+        let rendered = render("# Setup\n\n# Install\n\n# Setup\n");
+        assert_eq!(heading_ids(&rendered), ["setup", "install", "setup_2"]);
+
+        // Custom IDs are reserved before any IDs are generated.
+        let rendered = render("# Setup\n\n# Setup\n\n# Other {#setup_2}\n");
+        assert_eq!(heading_ids(&rendered), ["setup", "setup_3", "setup_2"]);
+    }
+
+    #[test]
+    fn duplicate_custom_heading_ids() {
+        let rendered = render("# One {#same}\n\n# Two {#same}\n");
+        assert_eq!(heading_ids(&rendered), ["same", "same"]);
+        assert_eq!(rendered.diagnostics.len(), 1);
+        assert_eq!(rendered.diagnostics[0].line(), Some(3));
+    }
+
+    #[test]
+    fn post_card_resolves_aliases() {
+        let mut link_targets = LinkTargets::new();
+        link_targets.insert(Id::new("a").unwrap(), "Title A", ["old"]);
+        let rendered = render_markdown(
+            &CodeBlockRenderer::new(),
+            &ShortcodeRegistry::new(),
+            &link_targets,
+            "{{ post id=\"old\" }}\n"
+        );
+        let html = rendered.html.into_string();
+        assert!(rendered.diagnostics.is_empty());
+        assert!(html.contains("href=\"/articles/a\""));
+        assert!(html.contains("Title A"));
+        assert_eq!(rendered.linked_posts, [Id::new("a").unwrap()]);
+    }
+
+    #[test]
+    fn broken_shortcode_links() {
+        let link_targets = LinkTargets::new().with_static_dir(PathBuf::from("/nonexistent"));
+        let rendered = render_markdown(
+            &CodeBlockRenderer::new(),
+            &ShortcodeRegistry::new(),
+            &link_targets,
+            "Text.\n\n{{ post id=\"missing\" }}\n\n{{ figure src=\"/static/x.png\" alt=\"\" }}\n"
+        );
+        assert_eq!(rendered.diagnostics.len(), 2);
+        assert!(rendered.diagnostics.iter().all(|diagnostic| diagnostic.severity() == Severity::Error));
+        assert!(rendered.diagnostics[0].message().starts_with("broken link: "));
+        assert_eq!((rendered.diagnostics[0].line(), rendered.diagnostics[0].column()), (Some(3), Some(1)));
+        assert!(rendered.diagnostics[1].message().starts_with("broken image: "));
+        assert_eq!((rendered.diagnostics[1].line(), rendered.diagnostics[1].column()), (Some(5), Some(16)));
+        assert_eq!(rendered.linked_posts, [Id::new("missing").unwrap()]);
+    }
+
+    #[test]
+    fn file_links_outside_dir_are_broken() {
+        let dir = TempDir::new("file_links_outside_dir");
+        let media_dir = dir.path().join("media");
+        let static_dir = dir.path().join("static");
+        fs::create_dir_all(&media_dir).unwrap();
+        fs::create_dir_all(&static_dir).unwrap();
+        fs::write(media_dir.join("a.png"), "").unwrap();
+        fs::write(static_dir.join("b.png"), "").unwrap();
+
+        // The files exist, so the links would not be reported if the paths were joined to the
+        // directories without being checked.
+        let secret = dir.path().join("secret.txt");
+        fs::write(&secret, "").unwrap();
+
+        let link_targets = LinkTargets::new()
+            .with_media_dir(media_dir)
+            .with_static_dir(static_dir);
+        let rendered = render_markdown(
+            &CodeBlockRenderer::new(),
+            &ShortcodeRegistry::new(),
+            &link_targets,
+            &format!(
+                "![a](/article_media/a.png) ![b](/static/b.png)\n\n\
+                 ![secret](/static/..%2Fsecret.txt)\n\n[secret](/article_media/{})\n",
+                secret.to_string_lossy().replace('/', "%2F")
+            )
+        );
+        assert_eq!(rendered.diagnostics.len(), 2);
+        assert!(rendered.diagnostics.iter().all(|diagnostic| diagnostic.severity() == Severity::Error));
+        assert!(rendered.diagnostics[0].message().starts_with("broken image: `../secret.txt` is outside"));
+        assert!(rendered.diagnostics[1].message().starts_with(&format!("broken link: `{}` is outside", secret.to_string_lossy())));
+    }
+}
@@ -2,42 +2,57 @@ use chrono::{DateTime, Utc};
 use libshire::{strings::ShString22, uuid::{Uuid, UuidV5Error}};
 use maud::{Markup, PreEscaped};
 
-use crate::{codeblock::CodeBlockRenderer, time::unix_epoch};
+use crate::{codeblock::CodeBlockRenderer, shortcode::ShortcodeRegistry, time::unix_epoch};
 
 use super::{
+    diagnostic::Diagnostic,
     error::Error,
-    header::Header,
+    header::{Asset, ChangelogEntry, Cover, Header, Layout, Series},
     id::Id,
-    source::PostSource,
+    link::LinkTargets,
+    source::{HeaderFormat, PostSource},
     render::render_markdown,
+    toc::TocEntry,
 };
 
 pub struct RenderedPost {
     uuid: Uuid,
     id: Id,
     header: Header,
-    updated: Option<DateTime<Utc>>,
+    header_format: HeaderFormat,
+    file_name: Option<String>,
+    modified: Option<DateTime<Utc>>,
     html: Markup,
+    extracted_summary: Option<String>,
+    toc: Vec<TocEntry>,
+    word_count: usize,
+    code_word_count: usize,
+    linked_posts: Vec<Id>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl RenderedPost {
     pub fn new_from_str(
         code_renderer: &CodeBlockRenderer,
+        shortcodes: &ShortcodeRegistry,
+        link_targets: &LinkTargets,
         namespace: Uuid,
         id: Id,
-        updated: Option<DateTime<Utc>>,
+        modified: Option<DateTime<Utc>>,
         source: &str
     ) -> Result<Self, Error>
     {
         let source = source.parse::<PostSource>()?;
-        Self::new_from_source(code_renderer, namespace, id, updated, source)
+        Self::new_from_source(code_renderer, shortcodes, link_targets, namespace, id, modified, source)
     }
 
     pub fn new_from_source(
         code_renderer: &CodeBlockRenderer,
+        shortcodes: &ShortcodeRegistry,
+        link_targets: &LinkTargets,
         namespace: Uuid,
         id: Id,
-        updated: Option<DateTime<Utc>>,
+        modified: Option<DateTime<Utc>>,
         source: PostSource
     ) -> Result<Self, Error>
     {
@@ -46,15 +61,47 @@ impl RenderedPost {
                 UuidV5Error::NameTooLong(len) => Error::IdTooLong(len),
             })?;
 
+        let rendered = render_markdown(code_renderer, shortcodes, link_targets, &source.markdown);
+        let markdown_line = source.markdown_line();
+
         Ok(Self {
             uuid,
             id,
             header: source.header,
-            updated,
-            html: render_markdown(code_renderer, &source.markdown), 
+            header_format: source.header_format,
+            file_name: None,
+            modified,
+            html: rendered.html,
+            extracted_summary: rendered.summary,
+            toc: rendered.toc,
+            word_count: rendered.word_count,
+            code_word_count: rendered.code_word_count,
+            linked_posts: rendered.linked_posts,
+            diagnostics: rendered.diagnostics
+                .into_iter()
+                .map(|diagnostic| diagnostic.offset_lines(markdown_line - 1))
+                .collect(),
         })
     }
 
+    /// Sets the author of the post if its header does not name any.
+    #[must_use]
+    pub fn with_default_author(mut self, author: &str) -> Self {
+        if self.header.authors.is_empty() {
+            self.header.authors.push(ShString22::new_from_str(author));
+        }
+        self
+    }
+
+    /// Sets the name of the file the post was read from.
+    #[must_use]
+    pub fn with_file_name(self, file_name: String) -> Self {
+        Self {
+            file_name: Some(file_name),
+            ..self
+        }
+    }
+
     #[inline]
     #[must_use]
     pub fn uuid(&self) -> Uuid {
@@ -73,6 +120,21 @@ impl RenderedPost {
         &self.header
     }
 
+    /// Returns the format the post's header is written in.
+    #[inline]
+    #[must_use]
+    pub fn header_format(&self) -> HeaderFormat {
+        self.header_format
+    }
+
+    /// Returns the name of the file the post was read from, if it was given with
+    /// `with_file_name`.
+    #[inline]
+    #[must_use]
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
     #[inline]
     #[must_use]
     pub fn title(&self) -> &str {
@@ -85,10 +147,25 @@ impl RenderedPost {
         self.header.subtitle()
     }
 
+    /// Returns the summary given in the post's header, or the summary extracted from the first
+    /// paragraph of the post if the header does not have one.
     #[inline]
     #[must_use]
-    pub fn author(&self) -> &str {
-        self.header.author()
+    pub fn summary(&self) -> Option<&str> {
+        self.header.summary()
+            .or(self.extracted_summary.as_deref())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn lang(&self) -> Option<&str> {
+        self.header.lang()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn authors(&self) -> &[ShString22] {
+        self.header.authors()
     }
 
     #[inline]
@@ -97,6 +174,12 @@ impl RenderedPost {
         self.header.tags()
     }
 
+    #[inline]
+    #[must_use]
+    pub fn aliases(&self) -> &[ShString22] {
+        self.header.aliases()
+    }
+
     #[inline]
     #[must_use]
     pub fn published(&self) -> DateTime<Utc> {
@@ -104,10 +187,69 @@ impl RenderedPost {
             .unwrap_or_else(unix_epoch)
     }
 
+    #[inline]
+    #[must_use]
+    pub fn draft(&self) -> bool {
+        self.header.draft()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn unlisted(&self) -> bool {
+        self.header.unlisted()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn pinned(&self) -> bool {
+        self.header.pinned()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn series(&self) -> Option<&Series> {
+        self.header.series()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn cover(&self) -> Option<&Cover> {
+        self.header.cover()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn layout(&self) -> Layout {
+        self.header.layout()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn styles(&self) -> &[Asset] {
+        self.header.styles()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn scripts(&self) -> &[Asset] {
+        self.header.scripts()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn changelog(&self) -> &[ChangelogEntry] {
+        self.header.changelog()
+    }
+
+    /// Returns the time the post was last updated. This is the `updated` field of the header if
+    /// there is one, then the most recent changelog entry, and finally the modification time of
+    /// the post's file, which may change without the post's content changing.
     #[inline]
     #[must_use]
     pub fn updated(&self) -> DateTime<Utc> {
-        self.updated
+        self.header.updated()
+            .or_else(|| self.changelog().iter().map(ChangelogEntry::date).max())
+            .or(self.modified)
             .unwrap_or_else(unix_epoch)
     }
 
@@ -116,4 +258,100 @@ impl RenderedPost {
     pub fn html(&self) -> PreEscaped<&str> {
         PreEscaped(&self.html.0)
     }
+
+    /// Returns the outline of the post's headings.
+    #[inline]
+    #[must_use]
+    pub fn toc(&self) -> &[TocEntry] {
+        &self.toc
+    }
+
+    /// Returns the number of words of prose in the post, not including code blocks.
+    #[inline]
+    #[must_use]
+    pub fn word_count(&self) -> usize {
+        self.word_count
+    }
+
+    /// Returns the number of words in the post's code blocks.
+    #[inline]
+    #[must_use]
+    pub fn code_word_count(&self) -> usize {
+        self.code_word_count
+    }
+
+    /// Returns the IDs of the posts which this post links to, including those which do not
+    /// exist. The post needs to be rendered again if any of them are added, removed
+    /// or renamed.
+    #[inline]
+    #[must_use]
+    pub fn linked_posts(&self) -> &[Id] {
+        &self.linked_posts
+    }
+
+    /// Returns the problems found when rendering the post's markdown which did not prevent it from
+    /// being rendered. Errors are problems which the post's author must fix, such as links to posts
+    /// or files which do not exist; warnings are problems such as invalid math or shortcodes.
+    #[inline]
+    #[must_use]
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the estimated number of minutes it takes to read the post at the given reading
+    /// speed, rounded up. Code blocks are not included, since how long they take to read varies
+    /// far more than prose does.
+    #[must_use]
+    pub fn reading_time_mins(&self, words_per_minute: u32) -> u32 {
+        let mins = (self.word_count as f64 / f64::from(words_per_minute.max(1))).ceil();
+        (mins as u32).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libshire::uuid::Uuid;
+
+    use crate::{codeblock::CodeBlockRenderer, shortcode::ShortcodeRegistry, test_util::toml_header};
+
+    use super::{Id, LinkTargets, RenderedPost};
+
+    fn post(header: &str, markdown: &str) -> RenderedPost {
+        RenderedPost::new_from_str(
+            &CodeBlockRenderer::new(),
+            &ShortcodeRegistry::new(),
+            &LinkTargets::new(),
+            Uuid::nil(),
+            Id::new("post").unwrap(),
+            None,
+            &format!("{}\n---\n{}", header, markdown)
+        ).unwrap()
+    }
+
+    fn authors(post: &RenderedPost) -> Vec<&str> {
+        post.authors().iter().map(|author| &**author).collect()
+    }
+
+    #[test]
+    fn header_summary_takes_precedence() {
+        let with_summary = post(&toml_header("summary = \"From the header.\""), "From the markdown.\n");
+        assert_eq!(with_summary.summary(), Some("From the header."));
+
+        let without_summary = post(&toml_header(""), "From the markdown.\n");
+        assert_eq!(without_summary.summary(), Some("From the markdown."));
+
+        assert_eq!(post(&toml_header(""), "# Heading\n").summary(), None);
+    }
+
+    #[test]
+    fn default_author_is_only_used_without_authors() {
+        let without_authors = post("title = \"Title\"", "").with_default_author("default");
+        assert_eq!(authors(&without_authors), ["default"]);
+
+        let with_author = post(&toml_header(""), "").with_default_author("default");
+        assert_eq!(authors(&with_author), ["me"]);
+
+        let with_authors = post("title = \"Title\"\nauthors = [\"ann\", \"bob\"]", "").with_default_author("default");
+        assert_eq!(authors(&with_authors), ["ann", "bob"]);
+    }
 }
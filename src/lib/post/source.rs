@@ -1,26 +1,117 @@
 use std::{fmt, str};
 
-use super::{error::Error, header::Header};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{diagnostic::Diagnostic, error::Error, header::Header, id::Id};
 
 const DELIM: &str = "\n---\n";
+const YAML_FENCE: &str = "---\n";
+const BOM: char = '\u{feff}';
 
 #[derive(Clone, Debug)]
 pub struct PostSource {
     pub(super) header: Header,
+    pub(super) header_format: HeaderFormat,
+    /// The text of the header as it was written, which is used when displaying the post source so
+    /// that formatting and comments are kept.
+    pub(super) raw_header: String,
     pub(super) markdown: String,
+    pub(super) warnings: Vec<Diagnostic>,
+    /// Whether the file started with a byte order mark.
+    bom: bool,
+    /// The text of the file as it was written, after any byte order mark. The source is parsed
+    /// with Windows line endings replaced by Unix line endings, and the line endings of the
+    /// original are restored when it is displayed.
+    original: String,
+}
+
+/// The format a post's header is written in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HeaderFormat {
+    /// A TOML header at the start of the file, followed by a `---` line.
+    Toml,
+    /// YAML front matter fenced by `---` lines, as used by Jekyll and Hugo.
+    Yaml,
+}
+
+impl HeaderFormat {
+    /// Returns the file extension used for posts whose header is in this format.
+    #[inline]
+    #[must_use]
+    pub fn file_extension(self) -> &'static str {
+        match self {
+            Self::Toml => super::POST_FILE_EXTENSION,
+            Self::Yaml => super::YAML_POST_FILE_EXTENSION,
+        }
+    }
 }
 
 impl PostSource {
+    /// Returns the ID of the post in the file with the given name and contents, or `None` if it is
+    /// not a post file. Files with the `.md` extension are only posts if they start with YAML
+    /// front matter, so that other markdown files such as a README can be kept in the posts
+    /// directory.
+    #[must_use]
+    pub fn post_id(file_name: &str, contents: &str) -> Option<Id> {
+        if Self::is_post_file(file_name, contents) {
+            Id::from_file_name(file_name)
+        } else {
+            None
+        }
+    }
+
+    fn is_post_file(file_name: &str, contents: &str) -> bool {
+        if file_name.ends_with(super::POST_FILE_EXTENSION) {
+            true
+        } else if file_name.ends_with(super::YAML_POST_FILE_EXTENSION) {
+            let contents = contents.strip_prefix(BOM).unwrap_or(contents);
+            contents.starts_with(YAML_FENCE) || contents.starts_with("---\r\n")
+        } else {
+            false
+        }
+    }
+
     #[inline]
     #[must_use]
     pub fn header(&self) -> &Header {
         &self.header
     }
 
+    /// Returns the format the header was written in, which is also the format it will be written
+    /// in when the post source is displayed.
     #[inline]
     #[must_use]
-    pub fn header_mut(&mut self) -> &mut Header {
-        &mut self.header
+    pub fn header_format(&self) -> HeaderFormat {
+        self.header_format
+    }
+
+    /// Sets the post's publish date. Only the date is changed in the header's text; the rest of
+    /// the header keeps its formatting, comments and key order.
+    pub fn set_published(&mut self, published: DateTime<Utc>) -> Result<(), Error> {
+        let published = published.to_rfc3339_opts(SecondsFormat::Secs, true);
+        // Jekyll and Hugo front matter calls the publish date `date`, which we accept as an
+        // alias, so edit that key instead if the header uses it.
+        self.set_header_string(&["published", "date"], &published)
+    }
+
+    /// Sets a string value in the header by editing the header's text, then parses the edited
+    /// header. `keys` is the name of the key followed by its aliases; the first of them which is
+    /// already in the header is edited, otherwise the first is added to the end of the header.
+    fn set_header_string(&mut self, keys: &[&str], value: &str) -> Result<(), Error> {
+        let raw_header = match self.header_format {
+            HeaderFormat::Toml => set_toml_string(&self.raw_header, keys, value)?,
+            HeaderFormat::Yaml => set_yaml_string(&self.raw_header, keys, value),
+        };
+
+        let (header, _) = match self.header_format {
+            HeaderFormat::Toml => Header::parse_toml(&raw_header),
+            HeaderFormat::Yaml => Header::parse_yaml(&raw_header),
+        }.map_err(|diagnostic| Error::Header(Box::new(diagnostic)))?;
+
+        self.header = header;
+        self.raw_header = raw_header;
+
+        Ok(())
     }
 
     #[inline]
@@ -34,24 +125,348 @@ impl PostSource {
     pub fn markdown_mut(&mut self) -> &mut String {
         &mut self.markdown
     }
+
+    /// Returns the line of the file that the markdown starts on, starting at 1.
+    #[must_use]
+    pub(super) fn markdown_line(&self) -> usize {
+        // The header is followed by the `---` line, and YAML front matter is also preceded by one.
+        let header_lines = self.raw_header.matches('\n').count() + 1;
+        match self.header_format {
+            HeaderFormat::Toml => header_lines + 2,
+            HeaderFormat::Yaml => header_lines + 3,
+        }
+    }
+
+    /// Returns the problems found when parsing the post which did not prevent it from being
+    /// parsed, such as unrecognised header keys.
+    #[inline]
+    #[must_use]
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
 }
 
 impl str::FromStr for PostSource {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (bom, s) = match s.strip_prefix(BOM) {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+
+        let original = s.to_owned();
+        let normalised;
+        let s = if s.contains("\r\n") {
+            normalised = s.replace("\r\n", "\n");
+            normalised.as_str()
+        } else {
+            s
+        };
+
+        // A TOML header cannot start with `---`, so if the file starts with a `---` line then it
+        // must be YAML front matter.
+        if let Some(s) = s.strip_prefix(YAML_FENCE) {
+            let (header, markdown) = s.split_once(DELIM)
+                .ok_or(Error::NoDelim)?;
+
+            // The YAML starts on the line after the opening fence, so the positions of any
+            // diagnostics need to be moved down a line to be relative to the whole file.
+            let (parsed_header, warnings) = Header::parse_yaml(header)
+                .map_err(|diagnostic| Error::Header(Box::new(diagnostic.offset_lines(1))))?;
+
+            return Ok(PostSource {
+                header: parsed_header,
+                header_format: HeaderFormat::Yaml,
+                raw_header: header.to_owned(),
+                markdown: markdown.to_owned(),
+                warnings: warnings
+                    .into_iter()
+                    .map(|warning| warning.offset_lines(1))
+                    .collect(),
+                bom,
+                original,
+            });
+        }
+
         let (header, markdown) = s.split_once(DELIM)
             .ok_or(Error::NoDelim)?;
 
+        let (parsed_header, warnings) = Header::parse_toml(header)
+            .map_err(|diagnostic| Error::Header(Box::new(diagnostic)))?;
+
         Ok(PostSource {
-            header: header.parse()?,
+            header: parsed_header,
+            header_format: HeaderFormat::Toml,
+            raw_header: header.to_owned(),
             markdown: markdown.to_owned(),
+            warnings,
+            bom,
+            original,
         })
     }
 }
 
 impl fmt::Display for PostSource {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {  
-        write!(f, "{}{}{}", self.header, DELIM, self.markdown)
+        let source = match self.header_format {
+            HeaderFormat::Toml => {
+                format!("{}{}{}", self.raw_header, DELIM, self.markdown)
+            },
+            HeaderFormat::Yaml => {
+                format!("{}{}{}{}", YAML_FENCE, self.raw_header, DELIM, self.markdown)
+            },
+        };
+
+        if self.bom {
+            write!(f, "{}", BOM)?;
+        }
+
+        if self.original.contains("\r\n") {
+            f.write_str(&restore_line_endings(&self.original, &source))
+        } else {
+            f.write_str(&source)
+        }
+    }
+}
+
+/// Returns `text`, which has Unix line endings, with each of its lines given the line ending it
+/// has in `original`. The lines at the start and end of `text` which are the same as in
+/// `original` are matched up with them, and each line in between is given the line ending of the
+/// line it replaces, or of the line before it if it is new. This way, editing one line of the
+/// file does not change the line endings of any other line.
+fn restore_line_endings(original: &str, text: &str) -> String {
+    fn split_lines(s: &str) -> Vec<(&str, &str)> {
+        s.split_inclusive('\n')
+            .map(|line| match line.strip_suffix("\r\n") {
+                Some(content) => (content, "\r\n"),
+                None => match line.strip_suffix('\n') {
+                    Some(content) => (content, "\n"),
+                    None => (line, ""),
+                },
+            })
+            .collect()
+    }
+
+    let old_lines = split_lines(original);
+    let new_lines = split_lines(text);
+
+    let same = |old: &(&str, &str), new: &(&str, &str)| old.0 == new.0 && old.1.is_empty() == new.1.is_empty();
+
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(old, new)| same(old, new))
+        .count();
+
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(old, new)| same(old, new))
+        .count();
+
+    let old_changed = &old_lines[prefix..old_lines.len() - suffix];
+    let new_changed = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut restored = String::with_capacity(original.len().max(text.len()));
+    let mut prev_ending = "\n";
+
+    for (i, (content, ending)) in old_lines[..prefix].iter().chain(new_changed).enumerate() {
+        let ending = match i.checked_sub(prefix) {
+            // The line is new or has been changed, so give it the line ending of the line it
+            // replaces, or otherwise of the line before it.
+            Some(i) if !ending.is_empty() => match old_changed.get(i) {
+                Some((_, old_ending)) if !old_ending.is_empty() => old_ending,
+                _ => prev_ending,
+            },
+            _ => ending,
+        };
+        restored.push_str(content);
+        restored.push_str(ending);
+        if !ending.is_empty() {
+            prev_ending = ending;
+        }
+    }
+
+    for (content, ending) in &old_lines[old_lines.len() - suffix..] {
+        restored.push_str(content);
+        restored.push_str(ending);
+    }
+
+    restored
+}
+
+fn set_toml_string(raw_header: &str, keys: &[&str], value: &str) -> Result<String, Error> {
+    let mut document = raw_header
+        .parse::<toml_edit::Document>()
+        .map_err(|err| Error::Header(Box::new(Diagnostic::error(err.to_string()))))?;
+
+    let existing = keys
+        .iter()
+        .copied()
+        .find(|key| document.contains_key(key));
+
+    match existing.and_then(|key| document[key].as_value_mut()) {
+        // Keep the whitespace and any comment around the old value.
+        Some(old_value) => {
+            let decor = old_value.decor().clone();
+            *old_value = toml_edit::Value::from(value);
+            *old_value.decor_mut() = decor;
+        },
+        None => {
+            document[keys[0]] = toml_edit::value(value);
+        },
+    }
+
+    // The header is followed directly by the delimiter, so it should not end with a newline.
+    Ok(document.to_string().trim_end_matches('\n').to_owned())
+}
+
+fn set_yaml_string(raw_header: &str, keys: &[&str], value: &str) -> String {
+    // Only top-level keys are edited, so the key must be at the start of the line.
+    fn key_value_split<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+        line.strip_prefix(key)?
+            .trim_start_matches(' ')
+            .strip_prefix(':')
+    }
+
+    let mut lines = raw_header
+        .lines()
+        .map(str::to_owned)
+        .collect::<Vec<String>>();
+
+    let existing = keys.iter().find_map(|key| {
+        lines.iter()
+            .position(|line| key_value_split(line, key).is_some())
+            .map(|i| (i, key))
+    });
+
+    match existing {
+        Some((i, key)) => {
+            // Keep any comment after the old value.
+            let comment = key_value_split(&lines[i], key)
+                .and_then(|old_value| old_value.find(" #").map(|pos| old_value[pos..].to_owned()))
+                .unwrap_or_default();
+            lines[i] = format!("{}: {}{}", key, value, comment);
+        },
+        None => {
+            lines.push(format!("{}: {}", keys[0], value));
+        },
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::PostSource;
+
+    #[test]
+    fn post_id() {
+        fn post_id(file_name: &str, contents: &str) -> Option<String> {
+            PostSource::post_id(file_name, contents).map(|id| id.to_string())
+        }
+
+        assert_eq!(post_id("post.toml.md", "title = \"Title\"\n---\n").as_deref(), Some("post"));
+        assert_eq!(post_id("post.md", "---\ntitle: Title\n---\n").as_deref(), Some("post"));
+        assert_eq!(post_id("README.md", "# Notes\n\n---\n"), None);
+        assert_eq!(post_id("notes.txt", "---\ntitle: Title\n---\n"), None);
+        assert_eq!(post_id("post.md", "---\r\ntitle: Title\r\n---\r\n").as_deref(), Some("post"));
+        assert_eq!(post_id("post.md", "\u{feff}---\ntitle: Title\n---\n").as_deref(), Some("post"));
+        assert_eq!(post_id("a.b.md", "---\ntitle: Title\n---\n"), None);
+    }
+
+    #[test]
+    fn crlf_and_bom_are_kept() {
+        let contents = "\u{feff}---\r\ntitle: Title\r\nauthor: Author\r\n---\r\nSome text.\r\n";
+        let mut source = contents.parse::<PostSource>().unwrap();
+        assert_eq!(source.header().title(), "Title");
+        assert_eq!(source.markdown(), "Some text.\n");
+        assert_eq!(source.to_string(), contents);
+
+        source.set_published(Utc.ymd(2022, 1, 2).and_hms(3, 4, 5)).unwrap();
+        assert_eq!(
+            source.to_string(),
+            "\u{feff}---\r\ntitle: Title\r\nauthor: Author\r\npublished: 2022-01-02T03:04:05Z\r\n---\r\nSome text.\r\n"
+        );
+    }
+
+    fn published() -> DateTime<Utc> {
+        Utc.ymd(2022, 1, 2).and_hms(3, 4, 5)
+    }
+
+    const TOML_HEADER: &str = "\
+# The post's title.
+title = 'Title'   # single quotes
+tags = [ 'a',  \"b\" ]
+author = \"me\"
+
+[series]
+name = 'Series'  # the series
+part = 2
+
+[[changelog]]
+date = '2022-01-01'
+note = 'First'";
+
+    #[test]
+    fn toml_header_is_kept_when_adding_published() {
+        let mut source = format!("{}\n---\nSome text.\n", TOML_HEADER).parse::<PostSource>().unwrap();
+        source.set_published(published()).unwrap();
+
+        assert_eq!(source.header().published(), Some(published()));
+        assert_eq!(
+            source.to_string(),
+            format!(
+                "{}\n---\nSome text.\n",
+                TOML_HEADER.replace("author = \"me\"\n", "author = \"me\"\npublished = \"2022-01-02T03:04:05Z\"\n")
+            )
+        );
+    }
+
+    #[test]
+    fn toml_header_is_kept_when_replacing_published() {
+        let header = TOML_HEADER.replace("tags", "published = '2020-01-01'  # old date\ntags");
+        let mut source = format!("{}\n---\nSome text.\n", header).parse::<PostSource>().unwrap();
+        source.set_published(published()).unwrap();
+
+        assert_eq!(source.header().published(), Some(published()));
+        assert_eq!(
+            source.to_string(),
+            format!(
+                "{}\n---\nSome text.\n",
+                header.replace("'2020-01-01'", "\"2022-01-02T03:04:05Z\"")
+            )
+        );
+    }
+
+    #[test]
+    fn toml_date_key_is_replaced() {
+        let mut source = "title = \"Title\"\ndate = '2020-01-01'\nauthor = \"me\"\n---\nSome text.\n"
+            .parse::<PostSource>()
+            .unwrap();
+        source.set_published(published()).unwrap();
+
+        assert_eq!(source.header().published(), Some(published()));
+        assert_eq!(
+            source.to_string(),
+            "title = \"Title\"\ndate = \"2022-01-02T03:04:05Z\"\nauthor = \"me\"\n---\nSome text.\n"
+        );
+    }
+
+    #[test]
+    fn mixed_line_endings_are_kept() {
+        let contents = "title = \"Title\"\r\nauthor = \"me\"\n---\r\nSome\ntext.\r\n";
+        let mut source = contents.parse::<PostSource>().unwrap();
+        assert_eq!(source.to_string(), contents);
+
+        source.set_published(published()).unwrap();
+        assert_eq!(
+            source.to_string(),
+            "title = \"Title\"\r\nauthor = \"me\"\npublished = \"2022-01-02T03:04:05Z\"\n---\r\nSome\ntext.\r\n"
+        );
     }
 }
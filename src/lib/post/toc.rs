@@ -0,0 +1,121 @@
+/// An entry in a post's table of contents, corresponding to one of the post's headings. Headings
+/// which appear after this one with a deeper level, up until the next heading with the same or a
+/// shallower level, are the entry's children.
+#[derive(Clone, Debug)]
+pub struct TocEntry {
+    level: u8,
+    id: String,
+    title: String,
+    children: Vec<TocEntry>,
+}
+
+impl TocEntry {
+    #[inline]
+    #[must_use]
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Returns the ID of the heading element, which can be used to link to the heading.
+    #[inline]
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn children(&self) -> &[TocEntry] {
+        &self.children
+    }
+}
+
+/// Builds a nested outline from a flat list of headings in the order they appear in the post.
+pub(super) fn build_outline(headings: Vec<Heading>) -> Vec<TocEntry> {
+    let mut outline: Vec<TocEntry> = Vec::new();
+
+    for heading in headings {
+        // Descend into the most recent entry for as long as it is a shallower heading than this
+        // one, so that this heading becomes a child of the closest preceding shallower heading.
+        let mut siblings = &mut outline;
+        while matches!(siblings.last(), Some(last) if last.level < heading.level) {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+
+        siblings.push(TocEntry {
+            level: heading.level,
+            id: heading.id,
+            title: heading.title,
+            children: Vec::new(),
+        });
+    }
+
+    outline
+}
+
+/// A heading collected while rendering a post.
+pub(super) struct Heading {
+    pub(super) level: u8,
+    pub(super) id: String,
+    pub(super) title: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_outline, Heading, TocEntry};
+
+    fn outline(headings: &[(u8, &str)]) -> Vec<TocEntry> {
+        build_outline(headings
+            .iter()
+            .map(|&(level, id)| Heading {
+                level,
+                id: id.to_owned(),
+                title: id.to_owned(),
+            })
+            .collect())
+    }
+
+    /// Writes the outline as the IDs of the entries, with the children of each entry in brackets.
+    fn shape(entries: &[TocEntry]) -> String {
+        entries
+            .iter()
+            .map(|entry| match entry.children() {
+                [] => entry.id().to_owned(),
+                children => format!("{}({})", entry.id(), shape(children)),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn siblings_keep_their_order() {
+        let outline = outline(&[(2, "a"), (3, "a1"), (3, "a2"), (2, "b"), (3, "b1"), (2, "c")]);
+        assert_eq!(shape(&outline), "a(a1 a2) b(b1) c");
+        assert_eq!(outline[0].children()[1].level(), 3);
+        assert_eq!(outline[0].children()[1].title(), "a2");
+    }
+
+    #[test]
+    fn skipped_levels() {
+        // An h4 directly under an h2 is still a child of the h2, and a following h3 is its
+        // sibling rather than its parent.
+        assert_eq!(shape(&outline(&[(2, "a"), (4, "a1"), (3, "a2"), (4, "a2i")])), "a(a1 a2(a2i))");
+    }
+
+    #[test]
+    fn starting_deeper_than_later_headings() {
+        // Headings before the first shallower heading stay at the top level.
+        assert_eq!(shape(&outline(&[(3, "intro"), (4, "detail"), (2, "a"), (3, "a1")])), "intro(detail) a(a1)");
+    }
+
+    #[test]
+    fn no_headings() {
+        assert!(outline(&[]).is_empty());
+    }
+}
@@ -0,0 +1,528 @@
+use std::{collections::HashMap, error, fmt};
+
+use maud::{html, Markup, PreEscaped};
+
+use crate::post::{article_path, LinkTargets};
+
+/// A component which can be used in a post's markdown with the syntax
+/// `{{ name key="value" other_key=value }}`. Shortcodes which wrap some markdown content, such
+/// as `details`, are opened with `{{ name ... }}` and closed with `{{ /name }}`.
+///
+/// A shortcode on its own in a paragraph is rendered in place of the paragraph, so shortcodes may
+/// render block elements such as `figure`.
+pub trait Shortcode: Send + Sync {
+    /// Renders the shortcode with the given arguments. For shortcodes which wrap some content,
+    /// this renders the part before the content. Shortcodes linking to other posts can look them
+    /// up in `link_targets`.
+    fn render(&self, args: &Args, link_targets: &LinkTargets) -> Result<Markup, ShortcodeError>;
+
+    /// Renders the part after the content for shortcodes which wrap some content. Shortcodes
+    /// which do not wrap any content return `None`, which is the default.
+    fn render_end(&self) -> Option<Markup> {
+        None
+    }
+
+    /// Returns the URLs which the shortcode links to or embeds when given the arguments, so that
+    /// they can be checked in the same way as the links in the markdown. The default is none.
+    fn links(&self, _args: &Args) -> Vec<(LinkKind, String)> {
+        Vec::new()
+    }
+}
+
+/// What a URL in a post is used for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinkKind {
+    Link,
+    Image,
+    Video,
+}
+
+impl LinkKind {
+    /// Returns the name of the kind of link, for use in messages.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Link => "link",
+            Self::Image => "image",
+            Self::Video => "video",
+        }
+    }
+}
+
+/// The shortcodes which can be used in posts, by name.
+pub struct ShortcodeRegistry {
+    shortcodes: HashMap<String, Box<dyn Shortcode>>,
+}
+
+impl ShortcodeRegistry {
+    /// Creates a registry containing the built-in shortcodes: `youtube`, `video`, `figure`,
+    /// `details` and `post`.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register("youtube", YouTube);
+        registry.register("video", Video);
+        registry.register("figure", Figure);
+        registry.register("details", Details);
+        registry.register("post", PostCard);
+        registry
+    }
+
+    /// Creates a registry containing no shortcodes.
+    pub fn empty() -> Self {
+        Self {
+            shortcodes: HashMap::new(),
+        }
+    }
+
+    /// Adds a shortcode to the registry, replacing any shortcode with the same name.
+    pub fn register<S>(&mut self, name: &str, shortcode: S)
+    where
+        S: Shortcode + 'static,
+    {
+        self.shortcodes.insert(name.to_owned(), Box::new(shortcode));
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn Shortcode> {
+        self.shortcodes.get(name).map(Box::as_ref)
+    }
+}
+
+impl Default for ShortcodeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A use of a shortcode in a post's markdown.
+#[derive(Clone, Debug)]
+pub struct Invocation {
+    name: String,
+    args: Args,
+    closing: bool,
+}
+
+impl Invocation {
+    /// Parses the text of a shortcode, which is everything between the `{{` and the `}}`.
+    pub fn parse(s: &str) -> Result<Self, ShortcodeError> {
+        let s = s.trim();
+
+        let (closing, s) = match s.strip_prefix('/') {
+            Some(s) => (true, s.trim_start()),
+            None => (false, s),
+        };
+
+        let name_len = s
+            .find(|c: char| !is_name_char(c))
+            .unwrap_or(s.len());
+
+        let (name, rest) = s.split_at(name_len);
+
+        if name.is_empty() {
+            return Err(ShortcodeError::new("expected the name of a shortcode after `{{`".to_owned()));
+        }
+
+        let args = Args::parse(rest)?;
+
+        if closing && !args.args.is_empty() {
+            return Err(ShortcodeError::new(format!("closing shortcode `{{{{ /{} }}}}` cannot have arguments", name)));
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            args,
+            closing,
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn args(&self) -> &Args {
+        &self.args
+    }
+
+    /// Returns whether this is the closing part of a shortcode which wraps some content, such as
+    /// `{{ /details }}`.
+    #[inline]
+    #[must_use]
+    pub fn closing(&self) -> bool {
+        self.closing
+    }
+}
+
+/// The arguments given to a shortcode. Arguments are written as `key="value"`, or `key=value`
+/// if the value contains no whitespace. A key on its own is a flag, whose value is empty.
+#[derive(Clone, Default, Debug)]
+pub struct Args {
+    args: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(s: &str) -> Result<Self, ShortcodeError> {
+        let mut args = Vec::new();
+        let mut chars = s.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            let mut key = String::new();
+            while let Some(c) = chars.next_if(|c| is_name_char(*c)) {
+                key.push(c);
+            }
+
+            if key.is_empty() {
+                match chars.next() {
+                    None => break,
+                    Some(c) => return Err(ShortcodeError::new(format!("unexpected {:?} in shortcode arguments", c))),
+                }
+            }
+
+            let mut value = String::new();
+
+            if chars.next_if_eq(&'=').is_some() {
+                if chars.next_if_eq(&'"').is_some() {
+                    // Backslashes escape the next character, so that quotes can be included.
+                    loop {
+                        match chars.next() {
+                            None => return Err(ShortcodeError::new(format!("unclosed quote in the value of `{}`", key))),
+                            Some('"') => break,
+                            Some('\\') => value.extend(chars.next()),
+                            Some(c) => value.push(c),
+                        }
+                    }
+                } else {
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                        value.push(c);
+                    }
+                }
+            }
+
+            if args.iter().any(|(existing, _)| *existing == key) {
+                return Err(ShortcodeError::new(format!("argument `{}` is given more than once", key)));
+            }
+
+            args.push((key, value));
+        }
+
+        Ok(Self { args })
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find(|(arg_key, _)| arg_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the value of an argument which the shortcode cannot be rendered without.
+    pub fn require(&self, key: &str) -> Result<&str, ShortcodeError> {
+        self.get(key)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| ShortcodeError::new(format!("missing argument `{}`", key)))
+    }
+
+    /// Returns whether a flag, or any other argument, is given.
+    #[inline]
+    #[must_use]
+    pub fn has(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns an error if any argument is not one of the given keys, so that misspelt arguments
+    /// are not silently ignored.
+    pub fn allow_only(&self, keys: &[&str]) -> Result<(), ShortcodeError> {
+        match self.args.iter().find(|(key, _)| !keys.contains(&key.as_str())) {
+            Some((key, _)) => Err(ShortcodeError::new(format!(
+                "unknown argument `{}`, expected one of: {}",
+                key,
+                keys.join(", ")
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// An error which prevented a shortcode from being rendered.
+#[derive(Clone, Debug)]
+pub struct ShortcodeError {
+    message: String,
+}
+
+impl ShortcodeError {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ShortcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for ShortcodeError {}
+
+/// Embeds a YouTube video: `{{ youtube id="..." title="..." start=30 }}`. The privacy-enhanced
+/// domain is used, so that YouTube does not set cookies until the video is played.
+struct YouTube;
+
+impl Shortcode for YouTube {
+    fn render(&self, args: &Args, _link_targets: &LinkTargets) -> Result<Markup, ShortcodeError> {
+        args.allow_only(&["id", "title", "start"])?;
+
+        let id = args.require("id")?;
+        if !id.chars().all(is_name_char) {
+            return Err(ShortcodeError::new(format!("invalid YouTube video ID `{}`", id)));
+        }
+
+        let mut src = format!("https://www.youtube-nocookie.com/embed/{}", id);
+        if let Some(start) = args.get("start") {
+            let start = start
+                .parse::<u32>()
+                .map_err(|_| ShortcodeError::new("`start` must be a whole number of seconds".to_owned()))?;
+            src.push_str(&format!("?start={}", start));
+        }
+
+        Ok(html! {
+            .embed .embed_video {
+                iframe
+                    src=(src)
+                    title=(args.get("title").unwrap_or("YouTube video"))
+                    loading="lazy"
+                    allow="accelerometer; encrypted-media; gyroscope; picture-in-picture"
+                    allowfullscreen {}
+            }
+        })
+    }
+}
+
+/// A video file with the browser's playback controls:
+/// `{{ video src="..." poster="..." caption="..." }}`.
+struct Video;
+
+impl Shortcode for Video {
+    fn render(&self, args: &Args, _link_targets: &LinkTargets) -> Result<Markup, ShortcodeError> {
+        args.allow_only(&["src", "poster", "caption", "loop", "muted", "autoplay"])?;
+
+        let src = args.require("src")?;
+
+        // Browsers only allow videos to play automatically if they are muted.
+        let autoplay = args.has("autoplay");
+
+        Ok(html! {
+            figure .video {
+                video
+                    src=(src)
+                    poster=[args.get("poster")]
+                    controls
+                    preload="metadata"
+                    loop[args.has("loop")]
+                    muted[args.has("muted") || autoplay]
+                    autoplay[autoplay]
+                    playsinline[autoplay] {
+                    a href=(src) { "Download the video" }
+                }
+                @if let Some(caption) = args.get("caption") {
+                    figcaption .quiet { (caption) }
+                }
+            }
+        })
+    }
+
+    fn links(&self, args: &Args) -> Vec<(LinkKind, String)> {
+        let src = args.get("src").map(|src| (LinkKind::Video, src.to_owned()));
+        let poster = args.get("poster").map(|poster| (LinkKind::Image, poster.to_owned()));
+        src.into_iter().chain(poster).collect()
+    }
+}
+
+/// An image with a caption: `{{ figure src="..." alt="..." caption="..." link="..." }}`.
+struct Figure;
+
+impl Shortcode for Figure {
+    fn render(&self, args: &Args, _link_targets: &LinkTargets) -> Result<Markup, ShortcodeError> {
+        args.allow_only(&["src", "alt", "caption", "link", "width", "height"])?;
+
+        let src = args.require("src")?;
+
+        // Images without alt text are inaccessible, so it must be given explicitly, even if it is
+        // empty because the image is decorative.
+        let alt = args.get("alt")
+            .ok_or_else(|| ShortcodeError::new("missing argument `alt`; use `alt=\"\"` for decorative images".to_owned()))?;
+
+        let img = html! {
+            img src=(src) alt=(alt) width=[args.get("width")] height=[args.get("height")] loading="lazy";
+        };
+
+        Ok(html! {
+            figure {
+                @if let Some(link) = args.get("link") {
+                    a href=(link) { (img) }
+                } @else {
+                    (img)
+                }
+                @if let Some(caption) = args.get("caption") {
+                    figcaption .quiet { (caption) }
+                }
+            }
+        })
+    }
+
+    fn links(&self, args: &Args) -> Vec<(LinkKind, String)> {
+        let src = args.get("src").map(|src| (LinkKind::Image, src.to_owned()));
+        let link = args.get("link").map(|link| (LinkKind::Link, link.to_owned()));
+        src.into_iter().chain(link).collect()
+    }
+}
+
+/// A collapsible section: `{{ details summary="..." open }}` followed by the section's markdown
+/// content and then `{{ /details }}`.
+struct Details;
+
+impl Shortcode for Details {
+    fn render(&self, args: &Args, _link_targets: &LinkTargets) -> Result<Markup, ShortcodeError> {
+        args.allow_only(&["summary", "open"])?;
+
+        let summary = args.get("summary").unwrap_or("Details");
+
+        // The `details` element is closed by `render_end`.
+        Ok(PreEscaped(format!(
+            "<details class=\"details\"{}>{}\n",
+            if args.has("open") { " open" } else { "" },
+            html! { summary { (summary) } }.into_string()
+        )))
+    }
+
+    fn render_end(&self) -> Option<Markup> {
+        Some(PreEscaped("</details>\n".to_owned()))
+    }
+}
+
+/// A card linking to another post: `{{ post id="..." title="..." description="..." }}`. The ID
+/// may be one of the post's aliases, and the title defaults to the post's title.
+struct PostCard;
+
+impl Shortcode for PostCard {
+    fn render(&self, args: &Args, link_targets: &LinkTargets) -> Result<Markup, ShortcodeError> {
+        args.allow_only(&["id", "title", "description"])?;
+
+        // Links to posts which do not exist are reported by checking the card's links, so the
+        // card is still rendered.
+        let id = args.require("id")?;
+        let (id, title) = match link_targets.resolve(id) {
+            Some((id, title)) => (&**id, title),
+            None => (id, id),
+        };
+
+        Ok(html! {
+            a .post_card href=(article_path(id)) {
+                span .post_card_title { (args.get("title").unwrap_or(title)) }
+                @if let Some(description) = args.get("description") {
+                    span .post_card_description .quiet { (description) }
+                }
+            }
+        })
+    }
+
+    fn links(&self, args: &Args) -> Vec<(LinkKind, String)> {
+        args.get("id")
+            .map(|id| (LinkKind::Link, article_path(id)))
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, Invocation};
+
+    #[test]
+    fn invocation() {
+        let invocation = Invocation::parse(" figure src=\"/a.png\" alt=\"\" ").unwrap();
+        assert_eq!(invocation.name(), "figure");
+        assert_eq!(invocation.args().get("src"), Some("/a.png"));
+        assert_eq!(invocation.args().get("alt"), Some(""));
+        assert!(!invocation.closing());
+
+        let invocation = Invocation::parse(" / details ").unwrap();
+        assert_eq!(invocation.name(), "details");
+        assert!(invocation.closing());
+
+        assert!(Invocation::parse(" ").is_err());
+        assert!(Invocation::parse(" \"details\" ").is_err());
+    }
+
+    #[test]
+    fn closing_invocation_with_args() {
+        let err = Invocation::parse(" /details open ").unwrap_err();
+        assert_eq!(err.message(), "closing shortcode `{{ /details }}` cannot have arguments");
+    }
+
+    #[test]
+    fn args() {
+        let args = Args::parse(" id=abc-1 title=\"A title\"  start=30").unwrap();
+        assert_eq!(args.get("id"), Some("abc-1"));
+        assert_eq!(args.get("title"), Some("A title"));
+        assert_eq!(args.get("start"), Some("30"));
+        assert_eq!(args.get("missing"), None);
+        assert!(args.allow_only(&["id", "title", "start"]).is_ok());
+        assert!(args.allow_only(&["id", "title"]).is_err());
+    }
+
+    #[test]
+    fn escaped_quotes() {
+        let args = Args::parse(r#"caption="A \"quoted\" \\ caption" alt="}}""#).unwrap();
+        assert_eq!(args.get("caption"), Some(r#"A "quoted" \ caption"#));
+        assert_eq!(args.get("alt"), Some("}}"));
+    }
+
+    #[test]
+    fn flags() {
+        let args = Args::parse(" open summary=\"More\" muted").unwrap();
+        assert!(args.has("open"));
+        assert!(args.has("muted"));
+        assert!(!args.has("loop"));
+        assert_eq!(args.get("open"), Some(""));
+        assert!(args.require("open").is_err());
+        assert_eq!(args.require("summary").unwrap(), "More");
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let err = Args::parse("alt=\"a\" alt=\"b\"").unwrap_err();
+        assert_eq!(err.message(), "argument `alt` is given more than once");
+        assert!(Args::parse("open open").is_err());
+    }
+
+    #[test]
+    fn unclosed_quote() {
+        let err = Args::parse("alt=\"a src=b").unwrap_err();
+        assert_eq!(err.message(), "unclosed quote in the value of `alt`");
+        assert!(Args::parse("alt=\"a\\\"").is_err());
+    }
+
+    #[test]
+    fn unexpected_chars() {
+        assert!(Args::parse("=value").is_err());
+        assert!(Args::parse("src=a ,alt=b").is_err());
+    }
+}
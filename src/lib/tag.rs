@@ -0,0 +1,253 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error,
+    fmt,
+};
+
+use libshire::strings::ShString22;
+
+/// The maximum number of characters in a normalised tag.
+const MAX_TAG_CHARS: usize = 64;
+
+/// Converts a tag to its normal form, so that tags which differ only in case, spacing or
+/// separators are treated as the same tag. Letters are lowercased, and runs of whitespace,
+/// hyphens and underscores become a single hyphen. Besides letters and digits, only `+`, `#` and
+/// `.` are allowed, so that tags such as `c++`, `c#` and `.net` can be written, but every tag must
+/// contain a letter or digit, so that tags such as `..` cannot be mistaken for relative paths in
+/// URLs.
+pub fn normalize(tag: &str) -> Result<ShString22, TagError> {
+    let mut normalized = String::with_capacity(tag.len());
+    let mut pending_hyphen = false;
+
+    for c in tag.trim().chars() {
+        if c.is_whitespace() || c == '-' || c == '_' {
+            pending_hyphen = true;
+        } else if c.is_alphanumeric() || matches!(c, '+' | '#' | '.') {
+            if pending_hyphen && !normalized.is_empty() {
+                normalized.push('-');
+            }
+            pending_hyphen = false;
+            normalized.extend(c.to_lowercase());
+        } else {
+            return Err(TagError::InvalidChar(c));
+        }
+    }
+
+    if normalized.is_empty() {
+        return Err(TagError::Empty);
+    }
+
+    if !normalized.chars().any(char::is_alphanumeric) {
+        return Err(TagError::NoAlphanumeric);
+    }
+
+    if normalized.chars().count() > MAX_TAG_CHARS {
+        return Err(TagError::TooLong);
+    }
+
+    Ok(ShString22::new_from_str(&normalized))
+}
+
+/// The set of known tags and the aliases which map to them.
+#[derive(Clone, Default, Debug)]
+pub struct TagRegistry {
+    canonical: HashSet<ShString22>,
+    aliases: HashMap<ShString22, ShString22>,
+}
+
+impl TagRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a known tag along with its aliases. The canonical name must already be in normal form,
+    /// so that it is shown the same way everywhere; the aliases are normalised. Each alias can only
+    /// belong to one tag, and cannot be the canonical name of another tag, so that which tag an
+    /// alias resolves to does not depend on the order the tags are inserted in. Nothing is added
+    /// if an error is returned.
+    pub fn insert<'a, I>(&mut self, canonical: &str, aliases: I) -> Result<(), TagError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let normalized = normalize(canonical)?;
+        if *normalized != *canonical {
+            return Err(TagError::NotNormalized(normalized));
+        }
+
+        if let Some(tag) = self.aliases.get(&normalized) {
+            return Err(TagError::TagIsAlias(tag.clone()));
+        }
+
+        let mut new_aliases = Vec::new();
+
+        for alias in aliases {
+            let alias = normalize(alias)?;
+
+            // An alias which is the same as the tag's own name has no effect.
+            if alias == normalized {
+                continue;
+            }
+
+            if self.canonical.contains(&alias) {
+                return Err(TagError::AliasIsTag(alias));
+            }
+
+            if let Some(tag) = self.aliases.get(&alias) {
+                return Err(TagError::DuplicateAlias(alias, tag.clone()));
+            }
+
+            new_aliases.push(alias);
+        }
+
+        for alias in new_aliases {
+            self.aliases.insert(alias, normalized.clone());
+        }
+
+        self.canonical.insert(normalized);
+
+        Ok(())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.canonical.is_empty()
+    }
+
+    /// Returns whether the given tag, which should be in normal form, is one of the known tags.
+    #[inline]
+    #[must_use]
+    pub fn is_known(&self, tag: &str) -> bool {
+        self.canonical.contains(tag)
+    }
+
+    /// Normalises the given tag and replaces it with its canonical name if it is an alias. The
+    /// returned tag is not necessarily known.
+    pub fn resolve(&self, tag: &str) -> Result<ShString22, TagError> {
+        let normalized = normalize(tag)?;
+        Ok(self.aliases
+            .get(&*normalized)
+            .cloned()
+            .unwrap_or(normalized))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TagError {
+    Empty,
+    /// The tag does not contain any letters or digits.
+    NoAlphanumeric,
+    TooLong,
+    InvalidChar(char),
+    NotNormalized(ShString22),
+    /// The tag is already an alias of the given tag.
+    TagIsAlias(ShString22),
+    /// The alias is the canonical name of another tag.
+    AliasIsTag(ShString22),
+    /// The alias already belongs to the given tag.
+    DuplicateAlias(ShString22, ShString22),
+}
+
+impl fmt::Display for TagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => {
+                write!(f, "tag is empty")
+            },
+            Self::NoAlphanumeric => {
+                write!(f, "tag does not contain any letters or digits")
+            },
+            Self::TooLong => {
+                write!(f, "tag is longer than {} characters", MAX_TAG_CHARS)
+            },
+            Self::InvalidChar(c) => {
+                write!(f, "tag contains invalid character {:?}", c)
+            },
+            Self::NotNormalized(normalized) => {
+                write!(f, "tag is not in normal form; it should be written as `{}`", normalized)
+            },
+            Self::TagIsAlias(tag) => {
+                write!(f, "tag is already an alias of tag `{}`", tag)
+            },
+            Self::AliasIsTag(alias) => {
+                write!(f, "alias `{}` is the name of another tag", alias)
+            },
+            Self::DuplicateAlias(alias, tag) => {
+                write!(f, "alias `{}` already belongs to tag `{}`", alias, tag)
+            },
+        }
+    }
+}
+
+impl error::Error for TagError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, TagError, TagRegistry};
+
+    #[test]
+    fn normalize_case_and_separators() {
+        assert_eq!(&*normalize("Rust").unwrap(), "rust");
+        assert_eq!(&*normalize("  Game  Dev ").unwrap(), "game-dev");
+        assert_eq!(&*normalize("game_-_dev").unwrap(), "game-dev");
+        assert_eq!(&*normalize("-web-").unwrap(), "web");
+        assert_eq!(&*normalize("C++").unwrap(), "c++");
+        assert_eq!(&*normalize("C#").unwrap(), "c#");
+        assert_eq!(&*normalize(".NET").unwrap(), ".net");
+        assert_eq!(&*normalize("Ünïcödé").unwrap(), "ünïcödé");
+    }
+
+    #[test]
+    fn normalize_rejects_invalid_tags() {
+        assert!(matches!(normalize(""), Err(TagError::Empty)));
+        assert!(matches!(normalize(" _- "), Err(TagError::Empty)));
+        assert!(matches!(normalize("rust/web"), Err(TagError::InvalidChar('/'))));
+        assert!(matches!(normalize("<b>"), Err(TagError::InvalidChar('<'))));
+        assert!(matches!(normalize("."), Err(TagError::NoAlphanumeric)));
+        assert!(matches!(normalize(".."), Err(TagError::NoAlphanumeric)));
+        assert!(matches!(normalize("..."), Err(TagError::NoAlphanumeric)));
+        assert!(matches!(normalize("+ #"), Err(TagError::NoAlphanumeric)));
+    }
+
+    #[test]
+    fn normalize_length_limit() {
+        assert!(normalize(&"a".repeat(64)).is_ok());
+        assert!(matches!(normalize(&"a".repeat(65)), Err(TagError::TooLong)));
+        // The limit applies to the normalised tag, so collapsed separators do not count.
+        assert!(normalize(&format!("{}    {}", "a".repeat(30), "b".repeat(30))).is_ok());
+        // The limit is in characters rather than bytes.
+        assert!(normalize(&"é".repeat(64)).is_ok());
+    }
+
+    #[test]
+    fn resolve() {
+        let mut registry = TagRegistry::new();
+        registry.insert("rust", ["rust-lang", "RustLang"]).unwrap();
+
+        assert_eq!(&*registry.resolve("Rust").unwrap(), "rust");
+        assert_eq!(&*registry.resolve("rust_lang").unwrap(), "rust");
+        assert_eq!(&*registry.resolve("rustlang").unwrap(), "rust");
+        assert_eq!(&*registry.resolve("Web Dev").unwrap(), "web-dev");
+        assert!(registry.is_known("rust"));
+        assert!(!registry.is_known("web-dev"));
+        assert!(registry.resolve("rust!").is_err());
+    }
+
+    #[test]
+    fn insert_rejects_conflicting_aliases() {
+        let mut registry = TagRegistry::new();
+        registry.insert("rust", ["rust-lang", "rust"]).unwrap();
+
+        assert!(matches!(registry.insert("Go", []), Err(TagError::NotNormalized(_))));
+        assert!(matches!(registry.insert("rust-lang", []), Err(TagError::TagIsAlias(_))));
+        assert!(matches!(registry.insert("golang", ["rust"]), Err(TagError::AliasIsTag(_))));
+        assert!(matches!(
+            registry.insert("go", ["go-lang", "Rust Lang"]),
+            Err(TagError::DuplicateAlias(alias, tag)) if &*alias == "rust-lang" && &*tag == "rust"
+        ));
+
+        // Nothing is added by a failed insert.
+        assert!(!registry.is_known("go"));
+        assert_eq!(&*registry.resolve("go-lang").unwrap(), "go-lang");
+    }
+}
@@ -0,0 +1,32 @@
+//! Helpers shared by the tests of the library and the blog server, which includes this file as
+//! one of its own modules.
+
+use std::{env, fs, path::{Path, PathBuf}, process};
+
+/// A directory for a test's files, which is removed when it is dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates an empty directory named after the test which uses it.
+    pub(crate) fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("blog_test_{}_{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Returns a TOML post header with a title and an author, followed by the given lines.
+pub(crate) fn toml_header(extra: &str) -> String {
+    format!("title = \"Title\"\nauthor = \"me\"\n{}", extra)
+}